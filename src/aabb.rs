@@ -0,0 +1,115 @@
+use super::*;
+
+#[derive(Clone)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self {
+            minimum: Point3::new(INFINITY, INFINITY, INFINITY),
+            maximum: Point3::new(-INFINITY, -INFINITY, -INFINITY),
+        }
+    }
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            minimum: a,
+            maximum: b,
+        }
+    }
+
+    pub fn min(&self) -> &Point3 {
+        &self.minimum
+    }
+
+    pub fn max(&self) -> &Point3 {
+        &self.maximum
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for a in 0..3 {
+            let inv_d = 1.0 / r.direction()[a];
+            let mut t0 = (self.min()[a] - r.origin()[a]) * inv_d;
+            let mut t1 = (self.max()[a] - r.origin()[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * &(&self.minimum + &self.maximum)
+    }
+
+    // Surface area of the box, used as the cost estimate by the BVH builder.
+    pub fn surface_area(&self) -> f64 {
+        let d = &self.maximum - &self.minimum;
+        if d.x() < 0.0 || d.y() < 0.0 || d.z() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    pub fn assign(&mut self, b: &Aabb) {
+        self.minimum = b.minimum.clone();
+        self.maximum = b.maximum.clone();
+    }
+}
+
+pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
+    let small = Point3::new(
+        box0.min().x().min(box1.min().x()),
+        box0.min().y().min(box1.min().y()),
+        box0.min().z().min(box1.min().z()),
+    );
+    let big = Point3::new(
+        box0.max().x().max(box1.max().x()),
+        box0.max().y().max(box1.max().y()),
+        box0.max().z().max(box1.max().z()),
+    );
+    Aabb::new(small, big)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let b = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3(0.0, 0.0, 1.0), 0.0);
+        assert!(b.hit(&r, 0.0, INFINITY));
+        assert!(!b.hit(&r, 0.0, 3.0));
+        let r = Ray::new(Point3::new(0.0, 2.0, -5.0), Vec3(0.0, 0.0, 1.0), 0.0);
+        assert!(!b.hit(&r, 0.0, INFINITY));
+    }
+
+    #[test]
+    fn test_surrounding_box() {
+        let b0 = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b1 = Aabb::new(Point3::new(-1.0, 0.5, 0.5), Point3::new(0.5, 2.0, 0.5));
+        let b = surrounding_box(&b0, &b1);
+        assert!(b.min().x() == -1.0 && b.min().y() == 0.0 && b.min().z() == 0.0);
+        assert!(b.max().x() == 1.0 && b.max().y() == 2.0 && b.max().z() == 1.0);
+    }
+
+    #[test]
+    fn test_surface_area() {
+        let b = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+        assert!(b.surface_area() == 22.0);
+        assert!(Aabb::default().surface_area() == 0.0);
+    }
+}
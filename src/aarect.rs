@@ -0,0 +1,246 @@
+use super::*;
+use std::sync::Arc;
+
+// Bounding boxes of the rectangles are padded by this much along the
+// thin axis so that they never have zero width.
+const PAD: f64 = 0.0001;
+
+pub struct XyRect {
+    pub mp: Arc<dyn Material>,
+    pub x0: f64,
+    pub x1: f64,
+    pub y0: f64,
+    pub y1: f64,
+    pub k: f64,
+}
+
+impl XyRect {
+    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            mp,
+            x0,
+            x1,
+            y0,
+            y1,
+            k,
+        }
+    }
+}
+
+impl Hitable for XyRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - r.origin().z()) / r.direction().z();
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let x = r.origin().x() + t * r.direction().x();
+        let y = r.origin().y() + t * r.direction().y();
+        if x < self.x0 || x > self.x1 || y < self.y0 || y > self.y1 {
+            return false;
+        }
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (y - self.y0) / (self.y1 - self.y0);
+        rec.t = t;
+        let outward_normal = Vec3::new(0.0, 0.0, 1.0);
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat_ptr = self.mp.clone();
+        rec.p = r.at(t);
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&Aabb::new(
+            Point3::new(self.x0, self.y0, self.k - PAD),
+            Point3::new(self.x1, self.y1, self.k + PAD),
+        ));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let mut rec: HitRecord = Default::default();
+        if !self.hit(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            0.001,
+            INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let (s, t) = sample_2d();
+        let random_point = Point3::new(
+            self.x0 + s * (self.x1 - self.x0),
+            self.y0 + t * (self.y1 - self.y0),
+            self.k,
+        );
+        &random_point - origin
+    }
+}
+
+pub struct XzRect {
+    pub mp: Arc<dyn Material>,
+    pub x0: f64,
+    pub x1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+}
+
+impl XzRect {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            mp,
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+        }
+    }
+}
+
+impl Hitable for XzRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - r.origin().y()) / r.direction().y();
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let x = r.origin().x() + t * r.direction().x();
+        let z = r.origin().z() + t * r.direction().z();
+        if x < self.x0 || x > self.x1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        let outward_normal = Vec3::new(0.0, 1.0, 0.0);
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat_ptr = self.mp.clone();
+        rec.p = r.at(t);
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&Aabb::new(
+            Point3::new(self.x0, self.k - PAD, self.z0),
+            Point3::new(self.x1, self.k + PAD, self.z1),
+        ));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let mut rec: HitRecord = Default::default();
+        if !self.hit(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            0.001,
+            INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let (s, t) = sample_2d();
+        let random_point = Point3::new(
+            self.x0 + s * (self.x1 - self.x0),
+            self.k,
+            self.z0 + t * (self.z1 - self.z0),
+        );
+        &random_point - origin
+    }
+}
+
+pub struct YzRect {
+    pub mp: Arc<dyn Material>,
+    pub y0: f64,
+    pub y1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+}
+
+impl YzRect {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            mp,
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+        }
+    }
+}
+
+impl Hitable for YzRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - r.origin().x()) / r.direction().x();
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let y = r.origin().y() + t * r.direction().y();
+        let z = r.origin().z() + t * r.direction().z();
+        if y < self.y0 || y > self.y1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+        rec.u = (y - self.y0) / (self.y1 - self.y0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        let outward_normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat_ptr = self.mp.clone();
+        rec.p = r.at(t);
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&Aabb::new(
+            Point3::new(self.k - PAD, self.y0, self.z0),
+            Point3::new(self.k + PAD, self.y1, self.z1),
+        ));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let mut rec: HitRecord = Default::default();
+        if !self.hit(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            0.001,
+            INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let (s, t) = sample_2d();
+        let random_point = Point3::new(
+            self.k,
+            self.y0 + s * (self.y1 - self.y0),
+            self.z0 + t * (self.z1 - self.z0),
+        );
+        &random_point - origin
+    }
+}
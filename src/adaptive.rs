@@ -0,0 +1,80 @@
+use super::*;
+
+// Luminance below which a pixel counts as black, so that relative error in
+// near-black pixels does not keep them sampling to the limit.
+pub const ADAPTIVE_BLACK_LEVEL: f64 = 0.01;
+
+// Running mean and variance of a pixel's sample luminance, updated one
+// sample at a time with Welford's method.
+#[derive(Clone, Default)]
+pub struct PixelStats {
+    pub count: i64,
+    pub mean: f64,
+    // Sum of squared differences from the mean.
+    pub m2: f64,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    // Unbiased sample variance.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return INFINITY;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+
+    // Estimated standard error of the mean, as a fraction of the mean.
+    pub fn relative_error(&self) -> f64 {
+        (self.variance() / self.count as f64).sqrt() / self.mean.max(ADAPTIVE_BLACK_LEVEL)
+    }
+}
+
+// Stops sampling a pixel once its relative error drops below threshold,
+// after at least min_samples.
+#[derive(Clone, Copy)]
+pub struct AdaptiveSampling {
+    pub threshold: f64,
+    pub min_samples: i64,
+}
+
+impl AdaptiveSampling {
+    pub fn new(threshold: f64, min_samples: i64) -> Self {
+        Self {
+            threshold,
+            min_samples,
+        }
+    }
+
+    pub fn is_converged(&self, stats: &PixelStats) -> bool {
+        stats.count >= self.min_samples.max(2) && stats.relative_error() < self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_stats() {
+        let xs = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut stats: PixelStats = Default::default();
+        for &x in xs.iter() {
+            stats.add(x);
+        }
+        assert!(stats.mean == 5.0 && (stats.variance() - 32.0 / 7.0).abs() < 1e-12);
+
+        let adaptive = AdaptiveSampling::new(0.11, 16);
+        assert!(!adaptive.is_converged(&stats));
+        for &x in xs.iter() {
+            stats.add(x);
+        }
+        assert!(adaptive.is_converged(&stats));
+    }
+}
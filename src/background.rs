@@ -0,0 +1,62 @@
+use super::*;
+
+// Radiance arriving along rays that escape the scene.
+pub trait Background: Send + Sync {
+    fn value(&self, r: &Ray) -> Color;
+
+    // Backgrounds that can be importance sampled as a light return true and
+    // override pdf_value and random, in the same way as Hitable.
+    fn is_sampleable(&self) -> bool {
+        false
+    }
+
+    fn pdf_value(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn random(&self) -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+}
+
+pub struct SolidBackground {
+    pub color: Color,
+}
+
+impl SolidBackground {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Background for SolidBackground {
+    fn value(&self, _r: &Ray) -> Color {
+        self.color.clone()
+    }
+}
+
+// Vertical blend between two colors, the classic white-to-blue sky.
+pub struct GradientBackground {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl GradientBackground {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Default for GradientBackground {
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for GradientBackground {
+    fn value(&self, r: &Ray) -> Color {
+        let unit_direction = unit_vector(r.direction());
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        &((1.0 - t) * &self.bottom) + &(t * &self.top)
+    }
+}
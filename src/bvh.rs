@@ -0,0 +1,153 @@
+use super::*;
+use std::sync::Arc;
+
+pub struct BvhNode {
+    pub left: Arc<dyn Hitable>,
+    pub right: Arc<dyn Hitable>,
+    pub bbox: Aabb,
+}
+
+// Builds a BVH over the objects in list that have bounding boxes. Objects
+// without one cannot go in the tree, so they are kept next to it in a flat
+// list; an empty list gives an empty world rather than a tree.
+pub fn build_bvh(list: &HitableList, time0: f64, time1: f64) -> HitableList {
+    let mut world = HitableList::new();
+    let mut bounded: Vec<(Arc<dyn Hitable>, Aabb)> = Vec::new();
+    for object in list.objects.iter() {
+        let mut bbox: Aabb = Default::default();
+        if object.bounding_box(time0, time1, &mut bbox) {
+            bounded.push((object.clone(), bbox));
+        } else {
+            world.add(object.clone());
+        }
+    }
+    if !bounded.is_empty() {
+        world.add(BvhNode::build_child(&mut bounded));
+    }
+    world
+}
+
+impl BvhNode {
+    // A tree over objects and their bounding boxes, of which there must be
+    // at least one.
+    fn build(objects: &mut [(Arc<dyn Hitable>, Aabb)]) -> Self {
+        let n = objects.len();
+        if n == 1 {
+            return Self {
+                left: objects[0].0.clone(),
+                right: objects[0].0.clone(),
+                bbox: objects[0].1.clone(),
+            };
+        }
+        if n == 2 {
+            return Self {
+                left: objects[0].0.clone(),
+                right: objects[1].0.clone(),
+                bbox: surrounding_box(&objects[0].1, &objects[1].1),
+            };
+        }
+
+        // Surface area heuristic: for each axis, sort by centroid and sweep
+        // every split position, keeping the one with the lowest expected cost.
+        let mut best_axis = 0;
+        let mut best_split = n / 2;
+        let mut best_cost = INFINITY;
+        let mut right_areas = vec![0.0; n];
+        for axis in 0..3 {
+            sort_by_centroid(objects, axis);
+
+            let mut right_box: Aabb = Default::default();
+            for i in (1..n).rev() {
+                right_box = surrounding_box(&right_box, &objects[i].1);
+                right_areas[i] = right_box.surface_area();
+            }
+
+            let mut left_box: Aabb = Default::default();
+            for i in 1..n {
+                left_box = surrounding_box(&left_box, &objects[i - 1].1);
+                let cost = i as f64 * left_box.surface_area() + (n - i) as f64 * right_areas[i];
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_axis = axis;
+                    best_split = i;
+                }
+            }
+        }
+
+        let bbox = objects.iter().fold(Aabb::default(), |acc, object| {
+            surrounding_box(&acc, &object.1)
+        });
+
+        sort_by_centroid(objects, best_axis);
+        let (left_objects, right_objects) = objects.split_at_mut(best_split);
+        Self {
+            left: Self::build_child(left_objects),
+            right: Self::build_child(right_objects),
+            bbox,
+        }
+    }
+
+    fn build_child(objects: &mut [(Arc<dyn Hitable>, Aabb)]) -> Arc<dyn Hitable> {
+        if objects.len() == 1 {
+            objects[0].0.clone()
+        } else {
+            Arc::new(Self::build(objects))
+        }
+    }
+}
+
+fn sort_by_centroid(objects: &mut [(Arc<dyn Hitable>, Aabb)], axis: usize) {
+    objects.sort_by(|a, b| {
+        a.1.centroid()[axis]
+            .partial_cmp(&b.1.centroid()[axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+impl Hitable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, t_min, t_max) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, t_min, t_max, rec);
+        let hit_right = self
+            .right
+            .hit(r, t_min, if hit_left { rec.t } else { t_max }, rec);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&self.bbox);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_bvh() {
+        assert!(build_bvh(&HitableList::new(), 0.0, 1.0).objects.is_empty());
+
+        // An empty list has no bounding box, so it stays out of the tree.
+        let mut list = HitableList::new();
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        list.add(Arc::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            0.5,
+            mat.clone(),
+        )));
+        list.add(Arc::new(Sphere::new(Vec3::new(0.0, 0.0, -4.0), 0.5, mat)));
+        list.add(Arc::new(HitableList::new()));
+        let world = build_bvh(&list, 0.0, 1.0);
+        assert!(world.objects.len() == 2);
+
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut rec: HitRecord = Default::default();
+        assert!(world.hit(&r, 0.001, INFINITY, &mut rec));
+        assert!(rec.t == 1.5);
+    }
+}
@@ -7,7 +7,6 @@ pub struct Camera {
     pub vertical: Vec3,
     pub u: Vec3,
     pub v: Vec3,
-    pub w: Vec3,
     pub lens_radius: f64,
     pub time0: f64,
     pub time1: f64,
@@ -30,6 +29,7 @@ impl Default for Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point3,
         lookat: Point3,
@@ -61,16 +61,15 @@ impl Camera {
         let time1 = t1;
 
         Self {
-            origin: origin,
-            lower_left_corner: lower_left_corner,
-            horizontal: horizontal,
-            vertical: vertical,
-            u: u,
-            v: v,
-            w: w,
-            lens_radius: lens_radius,
-            time0: time0,
-            time1: time1,
+            origin,
+            lower_left_corner,
+            horizontal,
+            vertical,
+            u,
+            v,
+            lens_radius,
+            time0,
+            time1,
         }
     }
 
@@ -82,7 +81,7 @@ impl Camera {
             &(&(&(&self.lower_left_corner + &(s * &self.horizontal)) + &(t * &self.vertical))
                 - &self.origin)
                 - &offset,
-            random_double_minmax(self.time0, self.time1),
+            self.time0 + (self.time1 - self.time0) * sample_1d(),
         )
     }
 }
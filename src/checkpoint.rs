@@ -0,0 +1,195 @@
+use super::*;
+use std::io::{Read, Write};
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RAY2CKPT";
+const CHECKPOINT_VERSION: u32 = 1;
+
+// A checkpointed render is split into at least this many passes, as
+// checkpoints are only saved between passes.
+pub const CHECKPOINT_PASSES: i64 = 16;
+
+// The state of an unfinished render. The random numbers of every sample are
+// derived from the seed and the sample's coordinates, so the seed stands in
+// for the generator state; settings describes everything else that decides
+// the pixels, to refuse resuming a different render.
+pub struct Checkpoint {
+    pub settings: String,
+    pub seed: u64,
+    // Samples per pixel taken so far, except by pixels adaptive sampling
+    // stopped early.
+    pub completed: i64,
+    pub framebuffer: Framebuffer,
+    pub stats: Vec<PixelStats>,
+}
+
+// A 64-bit hash of bytes, built on the splitmix64 finalizer.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash = mix64(bytes.len() as u64);
+    for chunk in bytes.chunks(8) {
+        let mut word = [0; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        hash = mix64(hash ^ u64::from_le_bytes(word));
+    }
+    hash
+}
+
+// Names each file with a hash of its contents, for the checkpoint settings,
+// so that a render does not resume after its scene file or assets changed.
+pub fn fingerprint_files(files: &[String]) -> std::io::Result<String> {
+    let mut fingerprint = String::new();
+    for filename in files {
+        let bytes = std::fs::read(filename)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))?;
+        fingerprint += &format!(" file={:?}:{:016x}", filename, hash_bytes(&bytes));
+    }
+    Ok(fingerprint)
+}
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+// Saves a checkpoint to filename, by way of a temporary file beside it so
+// that a crash while saving leaves the previous checkpoint intact.
+pub fn save_checkpoint(
+    filename: &str,
+    settings: &str,
+    seed: u64,
+    completed: i64,
+    fb: &Framebuffer,
+    stats: &[PixelStats],
+) -> std::io::Result<()> {
+    let partial = format!("{}.partial", filename);
+    std::fs::File::create(&partial)
+        .and_then(|file| {
+            let mut out = std::io::BufWriter::new(file);
+            write_checkpoint(&mut out, settings, seed, completed, fb, stats)
+        })
+        .and_then(|_| std::fs::rename(&partial, filename))
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))
+}
+
+pub fn load_checkpoint(filename: &str) -> std::io::Result<Checkpoint> {
+    std::fs::File::open(filename)
+        .and_then(|file| read_checkpoint(&mut std::io::BufReader::new(file)))
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))
+}
+
+// Little-endian binary: a header, then for each pixel, top row first, the
+// sum of its samples and its luminance statistics, all bit-exact.
+pub fn write_checkpoint<W: Write>(
+    out: &mut W,
+    settings: &str,
+    seed: u64,
+    completed: i64,
+    fb: &Framebuffer,
+    stats: &[PixelStats],
+) -> std::io::Result<()> {
+    out.write_all(CHECKPOINT_MAGIC)?;
+    out.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+    out.write_all(&(settings.len() as u64).to_le_bytes())?;
+    out.write_all(settings.as_bytes())?;
+    out.write_all(&seed.to_le_bytes())?;
+    out.write_all(&completed.to_le_bytes())?;
+    out.write_all(&(fb.width as u64).to_le_bytes())?;
+    out.write_all(&(fb.height as u64).to_le_bytes())?;
+    for (sum, pixel_stats) in fb.sums.iter().zip(stats.iter()) {
+        for x in [sum.x(), sum.y(), sum.z()] {
+            out.write_all(&x.to_le_bytes())?;
+        }
+        out.write_all(&pixel_stats.count.to_le_bytes())?;
+        out.write_all(&pixel_stats.mean.to_le_bytes())?;
+        out.write_all(&pixel_stats.m2.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> std::io::Result<f64> {
+    read_u64(reader).map(f64::from_bits)
+}
+
+pub fn read_checkpoint<R: Read>(reader: &mut R) -> std::io::Result<Checkpoint> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != CHECKPOINT_MAGIC {
+        return Err(invalid_data("not a checkpoint file"));
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    if u32::from_le_bytes(version) != CHECKPOINT_VERSION {
+        return Err(invalid_data("unsupported checkpoint version"));
+    }
+
+    let length = read_u64(reader)?;
+    if length > 1 << 16 {
+        return Err(invalid_data("corrupt checkpoint header"));
+    }
+    let mut settings = vec![0; length as usize];
+    reader.read_exact(&mut settings)?;
+    let settings =
+        String::from_utf8(settings).map_err(|_| invalid_data("corrupt checkpoint header"))?;
+    let seed = read_u64(reader)?;
+    let completed = read_u64(reader)? as i64;
+    let width = read_u64(reader)? as usize;
+    let height = read_u64(reader)? as usize;
+    if width == 0 || height == 0 || width.saturating_mul(height) > 1 << 30 {
+        return Err(invalid_data("corrupt checkpoint header"));
+    }
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut stats = vec![PixelStats::default(); width * height];
+    for (index, pixel_stats) in stats.iter_mut().enumerate() {
+        framebuffer.sums[index] =
+            Color::new(read_f64(reader)?, read_f64(reader)?, read_f64(reader)?);
+        pixel_stats.count = read_u64(reader)? as i64;
+        pixel_stats.mean = read_f64(reader)?;
+        pixel_stats.m2 = read_f64(reader)?;
+        framebuffer.samples[index] = pixel_stats.count;
+    }
+    Ok(Checkpoint {
+        settings,
+        seed,
+        completed,
+        framebuffer,
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut fb = Framebuffer::new(2, 1);
+        let mut stats = vec![PixelStats::default(); 2];
+        fb.sums[1] = Color::new(0.1, 0.2, 1e-300);
+        fb.samples[1] = 3;
+        for x in [0.1, 0.7, 0.3] {
+            stats[1].add(x);
+        }
+        let mut bytes = Vec::new();
+        write_checkpoint(&mut bytes, "width=2", 42, 3, &fb, &stats).unwrap();
+        let checkpoint = read_checkpoint(&mut &bytes[..]).unwrap();
+        assert!(checkpoint.settings == "width=2" && checkpoint.seed == 42);
+        assert!(checkpoint.completed == 3 && checkpoint.framebuffer.width == 2);
+        assert!(checkpoint.framebuffer.sums[1].z() == 1e-300);
+        assert!(checkpoint.framebuffer.samples[1] == 3 && checkpoint.stats[1].m2 == stats[1].m2);
+
+        bytes.truncate(bytes.len() - 1);
+        assert!(read_checkpoint(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        assert!(hash_bytes(b"{\"objects\": []}") == hash_bytes(b"{\"objects\": []}"));
+        assert!(hash_bytes(b"{\"objects\": []}") != hash_bytes(b"{\"objects\": [] }"));
+        assert!(hash_bytes(b"") != hash_bytes(b"\0"));
+    }
+}
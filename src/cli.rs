@@ -0,0 +1,564 @@
+use super::*;
+
+pub const USAGE: &str = "\
+Usage: ray2 [OPTIONS]
+
+Options:
+  --width <PIXELS>          image width [default: 1200]
+  --aspect <RATIO>          aspect ratio as W:H or a number [default: 16:9]
+  --spp <N>                 samples per pixel [default: 500]
+  --depth <N>               maximum ray bounces [default: 50]
+  --scene <N>               built-in scene, 1-9 [default: 1]
+  --scene-file <PATH>       JSON scene description, in place of --scene; its
+                            render settings replace the defaults below
+  --lookfrom <X,Y,Z>        camera position [default: per scene]
+  --lookat <X,Y,Z>          point the camera looks at [default: per scene]
+  --vfov <DEGREES>          vertical field of view [default: per scene]
+  --aperture <SIZE>         lens aperture, 0 for a pinhole [default: per scene]
+  --focus-dist <DISTANCE>   distance to the focal plane [default: per scene]
+  --env-map <PATH>          .hdr or .pfm environment lighting scene 7
+                            [default: assets/environment.hdr in the source
+                            tree]
+  --env-rotation <DEGREES>  environment rotation about +y [default: 0]
+  --env-intensity <SCALE>   environment brightness [default: 1]
+  --sun-elevation <DEGREES> sun height above the horizon in scene 8
+                            [default: 35]
+  --sun-azimuth <DEGREES>   sun direction, counterclockwise from +x seen from
+                            above [default: 60]
+  --turbidity <T>           haze, from 1.7 for clear air up [default: 3]
+  --sky-intensity <SCALE>   sun and sky brightness [default: 0.02]
+  --output <PATH>           .png, .ppm, .pfm, .hdr or .exr; - writes ASCII PPM
+                            to stdout [default: image.png]
+  --tone-map <OPERATOR>     clamp, reinhard, extended-reinhard, aces or hable
+                            [default: clamp]
+  --white-point <RADIANCE>  white point for extended-reinhard [default: 4]
+  --exposure <STOPS>        exposure compensation [default: 0]
+  --progressive <SECONDS>   render in passes that each double the samples per
+                            pixel, rewriting --output after a pass once this
+                            long has passed since the last write; 0 writes
+                            after every pass [default: off]
+  --checkpoint <PATH>       save the render's progress here between passes
+  --checkpoint-interval <SECONDS>
+                            least time between checkpoints [default: 300]
+  --resume <PATH>           continue the render saved in a checkpoint; the
+                            options that decide the pixels must match the
+                            original run, and its seed is used
+  --adaptive <ERROR>        stop sampling a pixel once the standard error of
+                            its mean is below this fraction of the mean;
+                            --spp becomes the maximum [default: off]
+  --min-spp <N>             samples every pixel takes before --adaptive may
+                            stop it [default: 16]
+  --sample-map <PATH>       also write each pixel's sample count as a
+                            fraction of --spp, in any --output format
+  --seed <N>                seed for scene generation and sampling; a seed
+                            gives the same image for any thread count
+                            [default: random, printed on stderr]
+  --sampler <NAME>          random, stratified, halton, sobol or blue-noise
+                            [default: sobol]
+  --threads <N>             render threads, at most 1024, 0 for every core
+                            [default: 0]
+  -h, --help                print this help
+";
+
+pub const SCENE_COUNT: i64 = 9;
+
+// Limits that keep the framebuffer and the set of render threads to a size
+// that can be allocated and spawned: 16384x16384 pixels, and 1024 threads.
+pub const MAX_IMAGE_PIXELS: i64 = 1 << 28;
+pub const MAX_THREADS: usize = 1024;
+
+// Found from the source tree, so that scene 7 runs from any directory.
+pub const DEFAULT_ENV_MAP: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/environment.hdr");
+
+// Render settings, from the command line over built-in defaults. Camera
+// fields left as None take the selected scene's own values.
+#[derive(Clone)]
+pub struct Settings {
+    pub image_width: i64,
+    pub aspect_ratio: f64,
+    pub samples_per_pixel: i64,
+    pub max_depth: i64,
+    pub scene: i64,
+    pub scene_file: Option<String>,
+    pub lookfrom: Option<Point3>,
+    pub lookat: Option<Point3>,
+    pub vfov: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focus_dist: Option<f64>,
+    // Equirectangular lighting for the lookdev scene.
+    pub env_map: String,
+    pub env_rotation: f64,
+    pub env_intensity: f64,
+    // Daylight for the sun and sky scene; angles are in degrees.
+    pub sun_elevation: f64,
+    pub sun_azimuth: f64,
+    pub turbidity: f64,
+    pub sky_intensity: f64,
+    // Empty for ASCII PPM on stdout.
+    pub output: String,
+    // Seconds between snapshots of a progressive render; None renders in a
+    // single pass.
+    pub progressive: Option<f64>,
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: f64,
+    pub resume: Option<String>,
+    // Relative error at which a pixel stops sampling; None for a fixed count.
+    pub adaptive_threshold: Option<f64>,
+    pub min_samples_per_pixel: i64,
+    pub sample_map: Option<String>,
+    pub tone_map: ToneMapOperator,
+    pub exposure: f64,
+    pub seed: Option<u64>,
+    pub sampler: SamplerType,
+    pub threads: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            image_width: 1200,
+            aspect_ratio: 16.0 / 9.0,
+            samples_per_pixel: 500,
+            max_depth: 50,
+            scene: 1,
+            scene_file: None,
+            lookfrom: None,
+            lookat: None,
+            vfov: None,
+            aperture: None,
+            focus_dist: None,
+            env_map: DEFAULT_ENV_MAP.to_string(),
+            env_rotation: 0.0,
+            env_intensity: 1.0,
+            sun_elevation: DEFAULT_SUN_ELEVATION,
+            sun_azimuth: DEFAULT_SUN_AZIMUTH,
+            turbidity: DEFAULT_TURBIDITY,
+            sky_intensity: DEFAULT_SKY_INTENSITY,
+            output: "image.png".to_string(),
+            progressive: None,
+            checkpoint: None,
+            checkpoint_interval: 300.0,
+            resume: None,
+            adaptive_threshold: None,
+            min_samples_per_pixel: 16,
+            sample_map: None,
+            tone_map: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            seed: None,
+            sampler: SamplerType::Sobol,
+            threads: 0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn image_height(&self) -> i64 {
+        (self.image_width as f64 / self.aspect_ratio) as i64
+    }
+
+    // The settings that decide the rendered pixels, apart from the seed, as
+    // text. Output, tone mapping, threads and pass options are left out, as
+    // they may change when a render is resumed.
+    pub fn description(&self) -> String {
+        let point = |p: &Option<Point3>| match p {
+            Some(p) => format!("{},{},{}", p.x(), p.y(), p.z()),
+            None => "default".to_string(),
+        };
+        format!(
+            "width={} height={} spp={} depth={} scene={} scene_file={:?} lookfrom={} \
+             lookat={} vfov={:?} aperture={:?} focus_dist={:?} env_map={:?} \
+             env_rotation={:?} env_intensity={:?} sun_elevation={:?} sun_azimuth={:?} \
+             turbidity={:?} sky_intensity={:?} sampler={:?} adaptive={:?} min_spp={}",
+            self.image_width,
+            self.image_height(),
+            self.samples_per_pixel,
+            self.max_depth,
+            self.scene,
+            self.scene_file,
+            point(&self.lookfrom),
+            point(&self.lookat),
+            self.vfov,
+            self.aperture,
+            self.focus_dist,
+            self.env_map,
+            self.env_rotation,
+            self.env_intensity,
+            self.sun_elevation,
+            self.sun_azimuth,
+            self.turbidity,
+            self.sky_intensity,
+            self.sampler,
+            self.adaptive_threshold,
+            self.min_samples_per_pixel
+        )
+    }
+}
+
+// Parses the arguments after the program name. Returns None when help was
+// requested, and a message naming the offending option for bad input.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Settings>, String> {
+    parse_args_with(args, Default::default())
+}
+
+// As parse_args, with the given settings in place of the built-in defaults.
+pub fn parse_args_with<I: IntoIterator<Item = String>>(
+    args: I,
+    defaults: Settings,
+) -> Result<Option<Settings>, String> {
+    let mut settings = defaults;
+    let mut tone_map = None;
+    let mut white_point = None;
+    let mut min_spp_given = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        // Accept both `--name value` and `--name=value`.
+        let (name, inline_value) = match arg.find('=') {
+            Some(eq) if arg.starts_with("--") => {
+                (arg[..eq].to_string(), Some(arg[eq + 1..].to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        if !name.starts_with("--") {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(format!("{} needs a value", name)),
+        };
+        let invalid = |expected: &str| {
+            format!(
+                "invalid value '{}' for {}: expected {}",
+                value, name, expected
+            )
+        };
+
+        match name.as_str() {
+            "--width" => {
+                settings.image_width = parse_number(&value)
+                    .filter(|&w: &i64| w >= 1)
+                    .ok_or_else(|| invalid("a positive integer"))?
+            }
+            "--aspect" => {
+                settings.aspect_ratio =
+                    parse_aspect(&value)
+                        .filter(|&a| a > 0.0 && a.is_finite())
+                        .ok_or_else(|| invalid("a positive ratio such as 16:9 or 1.5"))?
+            }
+            "--spp" => {
+                settings.samples_per_pixel = parse_number(&value)
+                    .filter(|&n: &i64| n >= 1)
+                    .ok_or_else(|| invalid("a positive integer"))?
+            }
+            "--depth" => {
+                settings.max_depth = parse_number(&value)
+                    .filter(|&n: &i64| n >= 1)
+                    .ok_or_else(|| invalid("a positive integer"))?
+            }
+            "--scene" => {
+                settings.scene = parse_number(&value)
+                    .filter(|&n: &i64| (1..=SCENE_COUNT).contains(&n))
+                    .ok_or_else(|| invalid(&format!("a scene number from 1 to {}", SCENE_COUNT)))?
+            }
+            "--scene-file" => settings.scene_file = Some(value.clone()),
+            "--lookfrom" => {
+                settings.lookfrom =
+                    Some(parse_point(&value).ok_or_else(|| invalid("three numbers x,y,z"))?)
+            }
+            "--lookat" => {
+                settings.lookat =
+                    Some(parse_point(&value).ok_or_else(|| invalid("three numbers x,y,z"))?)
+            }
+            "--vfov" => {
+                settings.vfov = Some(
+                    parse_number(&value)
+                        .filter(|&v: &f64| v > 0.0 && v < 180.0)
+                        .ok_or_else(|| invalid("an angle between 0 and 180 degrees"))?,
+                )
+            }
+            "--aperture" => {
+                settings.aperture = Some(
+                    parse_number(&value)
+                        .filter(|&a: &f64| a >= 0.0 && a.is_finite())
+                        .ok_or_else(|| invalid("a non-negative number"))?,
+                )
+            }
+            "--focus-dist" => {
+                settings.focus_dist = Some(
+                    parse_number(&value)
+                        .filter(|&d: &f64| d > 0.0 && d.is_finite())
+                        .ok_or_else(|| invalid("a positive number"))?,
+                )
+            }
+            "--env-map" => settings.env_map = value.clone(),
+            "--env-rotation" => {
+                settings.env_rotation = parse_number(&value)
+                    .filter(|&r: &f64| r.is_finite())
+                    .ok_or_else(|| invalid("an angle in degrees"))?
+            }
+            "--env-intensity" => {
+                settings.env_intensity = parse_number(&value)
+                    .filter(|&i: &f64| i >= 0.0 && i.is_finite())
+                    .ok_or_else(|| invalid("a non-negative number"))?
+            }
+            "--sun-elevation" => {
+                settings.sun_elevation = parse_number(&value)
+                    .filter(|&e: &f64| (-90.0..=90.0).contains(&e))
+                    .ok_or_else(|| invalid("an angle between -90 and 90 degrees"))?
+            }
+            "--sun-azimuth" => {
+                settings.sun_azimuth = parse_number(&value)
+                    .filter(|&a: &f64| a.is_finite())
+                    .ok_or_else(|| invalid("an angle in degrees"))?
+            }
+            "--turbidity" => {
+                settings.turbidity = parse_number(&value)
+                    .filter(|&t: &f64| (1.7..=20.0).contains(&t))
+                    .ok_or_else(|| invalid("a number from 1.7 to 20"))?
+            }
+            "--sky-intensity" => {
+                settings.sky_intensity = parse_number(&value)
+                    .filter(|&i: &f64| i >= 0.0 && i.is_finite())
+                    .ok_or_else(|| invalid("a non-negative number"))?
+            }
+            "--output" => {
+                settings.output = if value == "-" {
+                    String::new()
+                } else {
+                    value.clone()
+                };
+            }
+            "--progressive" => {
+                settings.progressive = Some(
+                    parse_number(&value)
+                        .filter(|&t: &f64| t >= 0.0 && t.is_finite())
+                        .ok_or_else(|| invalid("a non-negative number of seconds"))?,
+                )
+            }
+            "--checkpoint" => settings.checkpoint = Some(value.clone()),
+            "--checkpoint-interval" => {
+                settings.checkpoint_interval = parse_number(&value)
+                    .filter(|&t: &f64| t >= 0.0 && t.is_finite())
+                    .ok_or_else(|| invalid("a non-negative number of seconds"))?
+            }
+            "--resume" => settings.resume = Some(value.clone()),
+            "--adaptive" => {
+                settings.adaptive_threshold = Some(
+                    parse_number(&value)
+                        .filter(|&e: &f64| e > 0.0 && e.is_finite())
+                        .ok_or_else(|| invalid("a positive number"))?,
+                )
+            }
+            "--min-spp" => {
+                settings.min_samples_per_pixel = parse_number(&value)
+                    .filter(|&n: &i64| n >= 1)
+                    .ok_or_else(|| invalid("a positive integer"))?;
+                min_spp_given = true;
+            }
+            "--sample-map" => settings.sample_map = Some(value.clone()),
+            "--tone-map" => tone_map = Some(value.clone()),
+            "--white-point" => {
+                white_point = Some(
+                    parse_number(&value)
+                        .filter(|&w: &f64| w > 0.0 && w.is_finite())
+                        .ok_or_else(|| invalid("a positive number"))?,
+                )
+            }
+            "--exposure" => {
+                settings.exposure = parse_number(&value)
+                    .filter(|&e: &f64| e.is_finite())
+                    .ok_or_else(|| invalid("a number of stops"))?
+            }
+            "--seed" => {
+                settings.seed =
+                    Some(parse_number(&value).ok_or_else(|| invalid("a non-negative integer"))?)
+            }
+            "--sampler" => {
+                settings.sampler = match value.as_str() {
+                    "random" => SamplerType::Random,
+                    "stratified" => SamplerType::Stratified,
+                    "halton" => SamplerType::Halton,
+                    "sobol" => SamplerType::Sobol,
+                    "blue-noise" => SamplerType::BlueNoise,
+                    _ => return Err(invalid("random, stratified, halton, sobol or blue-noise")),
+                }
+            }
+            "--threads" => {
+                settings.threads = parse_number(&value)
+                    .filter(|&n: &usize| n <= MAX_THREADS)
+                    .ok_or_else(|| invalid(&format!("a thread count from 0 to {}", MAX_THREADS)))?
+            }
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+    }
+
+    if let Some(tone_map) = tone_map {
+        settings.tone_map = match tone_map.as_str() {
+            "clamp" => ToneMapOperator::Clamp,
+            "reinhard" => ToneMapOperator::Reinhard,
+            "extended-reinhard" => ToneMapOperator::ExtendedReinhard(white_point.unwrap_or(4.0)),
+            "aces" => ToneMapOperator::Aces,
+            "hable" => ToneMapOperator::Hable,
+            _ => {
+                return Err(format!(
+                    "invalid value '{}' for --tone-map: expected {}",
+                    tone_map, "clamp, reinhard, extended-reinhard, aces or hable"
+                ))
+            }
+        };
+    }
+
+    if min_spp_given && settings.min_samples_per_pixel > settings.samples_per_pixel {
+        return Err(format!(
+            "--min-spp {} is more than --spp {}",
+            settings.min_samples_per_pixel, settings.samples_per_pixel
+        ));
+    }
+    if white_point.is_some() && !matches!(settings.tone_map, ToneMapOperator::ExtendedReinhard(_)) {
+        return Err("--white-point needs --tone-map extended-reinhard".to_string());
+    }
+    for (option, filename) in [
+        ("--output", Some(&settings.output).filter(|f| !f.is_empty())),
+        ("--sample-map", settings.sample_map.as_ref()),
+    ] {
+        if let Some(filename) = filename.filter(|f| !is_image_format(f)) {
+            return Err(format!(
+                "unsupported image format for {} '{}': use .png, .ppm, .pfm, .hdr or .exr",
+                option, filename
+            ));
+        }
+    }
+    if settings.progressive.is_some() && settings.output.is_empty() {
+        return Err("--progressive needs an --output file to write snapshots to".to_string());
+    }
+    if settings.image_height() < 1 {
+        return Err(format!(
+            "image height rounds to zero for width {} and aspect ratio {}",
+            settings.image_width, settings.aspect_ratio
+        ));
+    }
+    if settings.image_width as f64 * settings.image_height() as f64 > MAX_IMAGE_PIXELS as f64 {
+        return Err(format!(
+            "a {}x{} image is too large: at most {} pixels are supported",
+            settings.image_width,
+            settings.image_height(),
+            MAX_IMAGE_PIXELS
+        ));
+    }
+    Ok(Some(settings))
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Option<T> {
+    s.trim().parse().ok()
+}
+
+fn parse_aspect(s: &str) -> Option<f64> {
+    match s.find(':') {
+        Some(colon) => {
+            let w: f64 = parse_number(&s[..colon])?;
+            let h: f64 = parse_number(&s[colon + 1..])?;
+            if h == 0.0 {
+                return None;
+            }
+            Some(w / h)
+        }
+        None => parse_number(s),
+    }
+}
+
+fn parse_point(s: &str) -> Option<Point3> {
+    let coordinates: Vec<f64> = s
+        .split(',')
+        .map(parse_number)
+        .collect::<Option<Vec<f64>>>()?;
+    if coordinates.len() != 3 || coordinates.iter().any(|c| !c.is_finite()) {
+        return None;
+    }
+    Some(Point3::new(coordinates[0], coordinates[1], coordinates[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Settings>, String> {
+        parse_args(args.split_whitespace().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let settings =
+            parse("--width 400 --aspect=4:3 --lookfrom 1,2,3 --tone-map aces --output -")
+                .unwrap()
+                .unwrap();
+        assert!(settings.image_width == 400 && settings.image_height() == 300);
+        assert!(settings.lookfrom.unwrap().y() == 2.0);
+        assert!(settings.tone_map == ToneMapOperator::Aces && settings.output.is_empty());
+        assert!(settings.sampler == SamplerType::Sobol);
+        let settings = parse("--sampler blue-noise").unwrap().unwrap();
+        assert!(settings.sampler == SamplerType::BlueNoise);
+        let settings = parse("--env-map sky.pfm --env-rotation -90 --env-intensity 0.5")
+            .unwrap()
+            .unwrap();
+        assert!(settings.env_map == "sky.pfm" && settings.env_rotation == -90.0);
+        assert!(settings.env_intensity == 0.5);
+        let settings = parse("--sun-elevation 5 --sun-azimuth 200 --turbidity 6")
+            .unwrap()
+            .unwrap();
+        assert!(settings.sun_elevation == 5.0 && settings.sun_azimuth == 200.0);
+        assert!(settings.turbidity == 6.0 && settings.sky_intensity == 0.02);
+        let settings = parse("--adaptive 0.02 --min-spp 8").unwrap().unwrap();
+        assert!(settings.adaptive_threshold == Some(0.02) && settings.min_samples_per_pixel == 8);
+        let resumed = parse("--adaptive 0.02 --min-spp 8 --threads 3 --resume a.ckpt")
+            .unwrap()
+            .unwrap();
+        assert!(resumed.description() == settings.description());
+        assert!(parse("--spp 8").unwrap().unwrap().description() != settings.description());
+        assert!(parse("--spp 4 --help").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(
+            parse("--spp 0").err().unwrap()
+                == "invalid value '0' for --spp: expected a positive integer"
+        );
+        assert!(parse("--scene 12").is_err());
+        assert!(parse("--lookat 1,2").is_err());
+        assert!(parse("--depth").err().unwrap() == "--depth needs a value");
+        assert!(parse("--bogus 1").err().unwrap() == "unknown option '--bogus'");
+        assert!(parse("--width 1 --aspect 2").is_err());
+        assert!(
+            parse("--width 4000000000").err().unwrap()
+                == "a 4000000000x2250000000 image is too large: at most 268435456 pixels are \
+                    supported"
+        );
+        assert!(parse("--width 100 --aspect 0.00001").is_err());
+        assert!(parse("--threads 100000000").is_err() && parse("--threads 1024").is_ok());
+        assert!(parse("--progressive 10 --output -").is_err());
+        assert!(parse("--env-intensity -1").is_err());
+        assert!(
+            parse("--output image.tga").err().unwrap()
+                == "unsupported image format for --output 'image.tga': use .png, .ppm, .pfm, .hdr \
+                    or .exr"
+        );
+        assert!(parse("--sample-map samples").is_err());
+        assert!(parse("--white-point 8").is_err());
+        assert!(
+            parse("--spp 8 --min-spp 16").err().unwrap() == "--min-spp 16 is more than --spp 8"
+        );
+        assert!(parse("--spp 8").is_ok() && parse("--min-spp 8 --spp 8").is_ok());
+        assert!(parse("--white-point 8 --tone-map aces").is_err());
+        assert!(
+            parse("--white-point 8 --tone-map extended-reinhard")
+                .unwrap()
+                .unwrap()
+                .tone_map
+                == ToneMapOperator::ExtendedReinhard(8.0)
+        );
+        assert!(parse("--sun-elevation 95").is_err() && parse("--turbidity 1").is_err());
+    }
+}
@@ -1,39 +1,37 @@
 use super::*;
 use std::io::Write;
 
+// Writes one pixel of an ASCII PPM after tone mapping and sRGB encoding.
 pub fn write_color(
     out: &mut std::io::BufWriter<std::io::StdoutLock>,
-    pixel_color: Color,
-    samples_per_pixel: i64,
+    pixel_color: &Color,
+    tone_mapper: &ToneMapper,
 ) -> std::io::Result<()> {
-    let mut r = pixel_color.x();
-    let mut g = pixel_color.y();
-    let mut b = pixel_color.z();
+    // Write the translated [0,255] value of each color component.
+    let [r, g, b] = to_srgb8(&tone_mapper.map(pixel_color));
+    writeln!(out, "{} {} {}", r, g, b)
+}
 
-    // Replace NaN components with zero. See explanation in Ray Tracing: The Rest of Your Life.
-    if r.is_nan() {
-        r = 0.0;
-    }
-    if g.is_nan() {
-        g = 0.0;
-    }
-    if b.is_nan() {
-        b = 0.0;
+// The sRGB transfer function, from linear [0,1] to encoded [0,1].
+pub fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
     }
+}
 
-    // Divide the color by the number of samples and gamma-correct for gamma=2.0.
-    let scale = 1.0 / samples_per_pixel as f64;
-    r = (scale * r).sqrt();
-    g = (scale * g).sqrt();
-    b = (scale * b).sqrt();
+// The inverse of linear_to_srgb.
+pub fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
 
-    // Write the translated [0,255] value of each color component.
-    writeln!(
-        out,
-        "{} {} {}",
-        (256.0 * clamp(r, 0.0, 0.999)) as i64,
-        (256.0 * clamp(g, 0.0, 0.999)) as i64,
-        (256.0 * clamp(b, 0.0, 0.999)) as i64,
-    )?;
-    Ok(())
+// 8-bit sRGB encoding of a linear color, clamped to [0,1] first.
+pub fn to_srgb8(c: &Color) -> [u8; 3] {
+    let encode = |x: f64| (256.0 * clamp(linear_to_srgb(clamp(x, 0.0, 1.0)), 0.0, 0.999)) as u8;
+    [encode(c.x()), encode(c.y()), encode(c.z())]
 }
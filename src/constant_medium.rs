@@ -0,0 +1,72 @@
+use super::*;
+use std::sync::Arc;
+
+// A volume of uniform density filling a convex boundary. Rays travelling
+// through it scatter after an exponentially distributed free-flight
+// distance, or pass straight through.
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hitable>,
+    pub phase_function: Arc<dyn Material>,
+    pub neg_inv_density: f64,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hitable>, density: f64, c: Color) -> Self {
+        Self::from_texture(boundary, density, Arc::new(SolidColor::new(c)))
+    }
+
+    pub fn from_texture(boundary: Arc<dyn Hitable>, density: f64, a: Arc<dyn Texture>) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Isotropic::from_texture(a)),
+            neg_inv_density: -1.0 / density,
+        }
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let mut rec1: HitRecord = Default::default();
+        let mut rec2: HitRecord = Default::default();
+
+        if !self.boundary.hit(r, -INFINITY, INFINITY, &mut rec1) {
+            return false;
+        }
+        if !self.boundary.hit(r, rec1.t + 0.0001, INFINITY, &mut rec2) {
+            return false;
+        }
+
+        if rec1.t < t_min {
+            rec1.t = t_min;
+        }
+        if rec2.t > t_max {
+            rec2.t = t_max;
+        }
+        if rec1.t >= rec2.t {
+            return false;
+        }
+        if rec1.t < 0.0 {
+            rec1.t = 0.0;
+        }
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
+        let hit_distance = self.neg_inv_density * sample_1d().ln();
+
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = rec1.t + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+
+        rec.normal = Vec3::new(1.0, 0.0, 0.0); // arbitrary
+        rec.front_face = true; // also arbitrary
+        rec.mat_ptr = self.phase_function.clone();
+        true
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.boundary.bounding_box(time0, time1, output_box)
+    }
+}
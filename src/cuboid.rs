@@ -0,0 +1,85 @@
+use super::*;
+use std::sync::Arc;
+
+// The six-sided box primitive. `box` is a reserved word in Rust, hence the name.
+pub struct Cuboid {
+    pub box_min: Point3,
+    pub box_max: Point3,
+    pub sides: HitableList,
+}
+
+impl Cuboid {
+    pub fn new(p0: &Point3, p1: &Point3, ptr: Arc<dyn Material>) -> Self {
+        // Construct the two opposite vertices with the minimum and maximum coordinates.
+        let min = Point3::new(p0.x().min(p1.x()), p0.y().min(p1.y()), p0.z().min(p1.z()));
+        let max = Point3::new(p0.x().max(p1.x()), p0.y().max(p1.y()), p0.z().max(p1.z()));
+
+        let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+        // Each face is wound so that its normal points out of the box.
+        let mut sides = HitableList::new();
+        sides.add(Arc::new(Quad::new(
+            Point3::new(min.x(), min.y(), max.z()),
+            dx.clone(),
+            dy.clone(),
+            ptr.clone(),
+        ))); // front
+        sides.add(Arc::new(Quad::new(
+            Point3::new(max.x(), min.y(), max.z()),
+            -&dz,
+            dy.clone(),
+            ptr.clone(),
+        ))); // right
+        sides.add(Arc::new(Quad::new(
+            Point3::new(max.x(), min.y(), min.z()),
+            -&dx,
+            dy.clone(),
+            ptr.clone(),
+        ))); // back
+        sides.add(Arc::new(Quad::new(
+            Point3::new(min.x(), min.y(), min.z()),
+            dz.clone(),
+            dy,
+            ptr.clone(),
+        ))); // left
+        sides.add(Arc::new(Quad::new(
+            Point3::new(min.x(), max.y(), max.z()),
+            dx.clone(),
+            -&dz,
+            ptr.clone(),
+        ))); // top
+        sides.add(Arc::new(Quad::new(
+            Point3::new(min.x(), min.y(), min.z()),
+            dx,
+            dz,
+            ptr,
+        ))); // bottom
+
+        Self {
+            box_min: min,
+            box_max: max,
+            sides,
+        }
+    }
+}
+
+impl Hitable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.sides.hit(r, t_min, t_max, rec)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&Aabb::new(self.box_min.clone(), self.box_max.clone()));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.sides.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.sides.random(origin)
+    }
+}
@@ -0,0 +1,209 @@
+use super::*;
+
+// Equirectangular (latitude-longitude) environment light. The top image row
+// maps to +y and the image's center column to +x, before rotating the map
+// about the y axis.
+pub struct EnvironmentMap {
+    pub image: HdrImage,
+    pub intensity: f64,
+    pub to_world: Transform,
+    pub to_local: Transform,
+    // Cumulative distributions for importance sampling, proportional to
+    // luminance times the solid angle of each pixel.
+    pub marginal_cdf: Vec<f64>,
+    pub conditional_cdf: Vec<f64>,
+    pub pixel_pdf: Vec<f64>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: HdrImage, rotation: f64, intensity: f64) -> Self {
+        let (width, height) = (image.width, image.height);
+        let mut weights = vec![0.0; width * height];
+        for j in 0..height {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            for i in 0..width {
+                weights[j * width + i] = luminance(image.pixel(i, j)) * sin_theta;
+            }
+        }
+
+        let mut conditional_cdf = vec![0.0; width * height];
+        let mut row_sums = vec![0.0; height];
+        for j in 0..height {
+            let mut sum = 0.0;
+            for i in 0..width {
+                sum += weights[j * width + i];
+                conditional_cdf[j * width + i] = sum;
+            }
+            row_sums[j] = sum;
+        }
+        let total: f64 = row_sums.iter().sum();
+        let mut marginal_cdf = vec![0.0; height];
+        let mut sum = 0.0;
+        for j in 0..height {
+            sum += row_sums[j];
+            marginal_cdf[j] = sum;
+        }
+
+        // Density of each pixel relative to uniform over the image; an all
+        // black map falls back to uniform sampling.
+        let pixel_pdf = weights
+            .iter()
+            .map(|w| {
+                if total > 0.0 {
+                    w * (width * height) as f64 / total
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+
+        let to_world = Transform::rotate_y(rotation);
+        let to_local = Transform::rotate_y(-rotation);
+        Self {
+            image,
+            intensity,
+            to_world,
+            to_local,
+            marginal_cdf,
+            conditional_cdf,
+            pixel_pdf,
+        }
+    }
+
+    pub fn load(filename: &str, rotation: f64, intensity: f64) -> std::io::Result<Self> {
+        Ok(Self::new(load_hdr_image(filename)?, rotation, intensity))
+    }
+
+    // Image coordinates in [0,1) x [0,1) of a world-space direction.
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = unit_vector(&self.to_local.transform_vector(direction));
+        let u = ((-d.z()).atan2(d.x()) + PI) / (2.0 * PI);
+        let v = clamp(d.y(), -1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = 2.0 * PI * u - PI;
+        let theta = PI * v;
+        let local = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            -theta.sin() * phi.sin(),
+        );
+        self.to_world.transform_vector(&local)
+    }
+
+    fn pixel_index(&self, u: f64, v: f64) -> (usize, usize) {
+        let i = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        (i, j)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, r: &Ray) -> Color {
+        let (u, v) = self.direction_to_uv(r.direction());
+        let (i, j) = self.pixel_index(u, v);
+        self.intensity * self.image.pixel(i, j)
+    }
+
+    fn is_sampleable(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let (i, j) = self.pixel_index(u, v);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // Convert from density over the unit square to solid angle.
+        self.pixel_pdf[j * self.image.width + i] / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random(&self) -> Vec3 {
+        let (width, height) = (self.image.width, self.image.height);
+        let total = self.marginal_cdf[height - 1];
+        if total <= 0.0 {
+            let (u, v) = sample_2d();
+            return self.uv_to_direction(u, v);
+        }
+        let (r1, r2) = sample_2d();
+        let j = sample_cdf(&self.marginal_cdf, r1 * total);
+        let row = &self.conditional_cdf[j * width..(j + 1) * width];
+        let i = sample_cdf(row, r2 * row[width - 1]);
+
+        let (du, dv) = sample_2d();
+        let u = (i as f64 + du) / width as f64;
+        let v = (j as f64 + dv) / height as f64;
+        self.uv_to_direction(u, v)
+    }
+}
+
+pub fn luminance(c: &Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+// Index of the first entry of an increasing cdf that exceeds x.
+fn sample_cdf(cdf: &[f64], x: f64) -> usize {
+    let index = cdf.partition_point(|&c| c <= x);
+    index.min(cdf.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uv_round_trip() {
+        let image = HdrImage {
+            width: 4,
+            height: 2,
+            data: vec![Color::new(1.0, 1.0, 1.0); 8],
+        };
+        let env = EnvironmentMap::new(image, 30.0, 1.0);
+        let d = unit_vector(&Vec3(0.3, -0.5, 0.8));
+        let (u, v) = env.direction_to_uv(&d);
+        assert!((&env.uv_to_direction(u, v) - &d).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_pdf_matches_sampling() {
+        // A single bright pixel should receive nearly every sample.
+        let mut data = vec![Color::new(0.01, 0.01, 0.01); 32];
+        data[8 * 2 + 5] = Color::new(100.0, 100.0, 100.0);
+        let image = HdrImage {
+            width: 8,
+            height: 4,
+            data,
+        };
+        let env = EnvironmentMap::new(image, 0.0, 1.0);
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let (u, v) = env.direction_to_uv(&env.random());
+            if env.pixel_index(u, v) == (5, 2) {
+                hits += 1;
+            }
+        }
+        assert!(hits > 950);
+
+        // The pdf integrates to one over the sphere.
+        let n = 200;
+        let mut integral = 0.0;
+        for a in 0..n {
+            for b in 0..2 * n {
+                let theta = PI * (a as f64 + 0.5) / n as f64;
+                let phi = 2.0 * PI * (b as f64 + 0.5) / (2 * n) as f64;
+                let d = Vec3(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let d_omega = theta.sin() * (PI / n as f64) * (PI / n as f64);
+                integral += env.pdf_value(&d) * d_omega;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,60 @@
+use super::*;
+
+// Accumulated radiance for a whole image, rows stored top to bottom. Each
+// pixel keeps the sum of its samples and how many were taken, so pixels can
+// receive different sample counts.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub sums: Vec<Color>,
+    pub samples: Vec<i64>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Color::new(0.0, 0.0, 0.0); width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    // Mean radiance of a pixel, with NaN components replaced by zero. See
+    // explanation in Ray Tracing: The Rest of Your Life.
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        let index = j * self.width + i;
+        if self.samples[index] == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let mean = &self.sums[index] / self.samples[index] as f64;
+        let finite = |c: f64| if c.is_nan() { 0.0 } else { c };
+        Color::new(finite(mean.x()), finite(mean.y()), finite(mean.z()))
+    }
+
+    // Each pixel's sample count as a grey level, as a fraction of
+    // max_samples.
+    pub fn sample_map(&self, max_samples: i64) -> Framebuffer {
+        let mut map = Framebuffer::new(self.width, self.height);
+        for (index, &samples) in self.samples.iter().enumerate() {
+            let level = samples as f64 / max_samples as f64;
+            map.sums[index] = Color::new(level, level, level);
+            map.samples[index] = 1;
+        }
+        map
+    }
+
+    pub fn to_hdr_image(&self) -> HdrImage {
+        let mut data = Vec::with_capacity(self.width * self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                data.push(self.pixel(i, j));
+            }
+        }
+        HdrImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+}
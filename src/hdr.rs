@@ -0,0 +1,379 @@
+use super::*;
+use std::io::{BufRead, Read, Write};
+
+// Linear floating-point RGB image, rows stored top to bottom.
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Color>,
+}
+
+impl HdrImage {
+    pub fn pixel(&self, i: usize, j: usize) -> &Color {
+        &self.data[j * self.width + i]
+    }
+}
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// Loads a Radiance RGBE (.hdr) or portable float map (.pfm) by extension.
+pub fn load_hdr_image(filename: &str) -> std::io::Result<HdrImage> {
+    let result = std::fs::File::open(filename).and_then(|file| {
+        let mut reader = std::io::BufReader::new(file);
+        if filename.to_lowercase().ends_with(".pfm") {
+            read_pfm(&mut reader)
+        } else {
+            read_radiance_hdr(&mut reader)
+        }
+    });
+    result.map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))
+}
+
+pub fn rgbe_to_color(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let f = 2.0_f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+pub fn read_radiance_hdr<R: BufRead>(reader: &mut R) -> std::io::Result<HdrImage> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+
+    // Header variables run up to a blank line.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("unexpected end of header"));
+        }
+        let l = line.trim();
+        if l.is_empty() {
+            break;
+        }
+        if l.starts_with("FORMAT=") && l != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("unsupported pixel format"));
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(invalid_data("unsupported image orientation"));
+    }
+    let height: usize = tokens[1]
+        .parse()
+        .map_err(|_| invalid_data("invalid height"))?;
+    let width: usize = tokens[3]
+        .parse()
+        .map_err(|_| invalid_data("invalid width"))?;
+
+    let mut data = Vec::with_capacity(width * height);
+    let mut scanline = vec![0u8; width * 4];
+    for _ in 0..height {
+        read_rgbe_scanline(reader, &mut scanline, width)?;
+        data.extend(scanline.chunks(4).map(rgbe_to_color));
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        data,
+    })
+}
+
+fn read_rgbe_scanline<R: Read>(
+    reader: &mut R,
+    scanline: &mut [u8],
+    width: usize,
+) -> std::io::Result<()> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header)?;
+
+    // Scanlines outside the run-length encodable range are stored flat.
+    let is_rle = (8..32768).contains(&width)
+        && header[0] == 2
+        && header[1] == 2
+        && ((header[2] as usize) << 8 | header[3] as usize) == width;
+    if !is_rle {
+        scanline[..4].copy_from_slice(&header);
+        return reader.read_exact(&mut scanline[4..]);
+    }
+
+    // Each of the four components is run-length encoded separately.
+    let mut component = vec![0u8; width];
+    for c in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let count = count[0] as usize;
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    return Err(invalid_data("bad scanline data"));
+                }
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value)?;
+                component[x..x + run].iter_mut().for_each(|v| *v = value[0]);
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("bad scanline data"));
+                }
+                reader.read_exact(&mut component[x..x + count])?;
+                x += count;
+            }
+        }
+        for (i, value) in component.iter().enumerate() {
+            scanline[i * 4 + c] = *value;
+        }
+    }
+    Ok(())
+}
+
+fn read_token<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0].is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            return Ok(token);
+        }
+        token.push(byte[0] as char);
+    }
+}
+
+pub fn read_pfm<R: BufRead>(reader: &mut R) -> std::io::Result<HdrImage> {
+    let channels = match read_token(reader)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file")),
+    };
+    let width: usize = read_token(reader)?
+        .parse()
+        .map_err(|_| invalid_data("invalid width"))?;
+    let height: usize = read_token(reader)?
+        .parse()
+        .map_err(|_| invalid_data("invalid height"))?;
+    let scale: f64 = read_token(reader)?
+        .parse()
+        .map_err(|_| invalid_data("invalid scale"))?;
+    // A negative scale marks little-endian data.
+    let little_endian = scale < 0.0;
+
+    let mut bytes = vec![0u8; width * height * channels * 4];
+    reader.read_exact(&mut bytes)?;
+    let floats: Vec<f64> = bytes
+        .chunks(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(b) as f64
+            } else {
+                f32::from_be_bytes(b) as f64
+            }
+        })
+        .collect();
+
+    // PFM rows run bottom to top.
+    let mut data = Vec::with_capacity(width * height);
+    for j in (0..height).rev() {
+        for i in 0..width {
+            let k = (j * width + i) * channels;
+            data.push(if channels == 3 {
+                Color::new(floats[k], floats[k + 1], floats[k + 2])
+            } else {
+                Color::new(floats[k], floats[k], floats[k])
+            });
+        }
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        data,
+    })
+}
+
+// Shared exponent encoding of a linear color; the inverse of rgbe_to_color.
+// Channels too bright to encode, infinity included, saturate at the largest
+// RGBE value, and NaN channels encode as zero.
+pub fn color_to_rgbe(c: &Color) -> [u8; 4] {
+    let max = 255.0 / 256.0 * 2.0_f64.powi(127);
+    let channel = |x: f64| if x.is_nan() { 0.0 } else { x.min(max) };
+    let c = Color::new(channel(c.x()), channel(c.y()), channel(c.z()));
+    let v = c.x().max(c.y()).max(c.z());
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2.0_f64.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2.0_f64.powi(e);
+    let byte = |x: f64| (x.max(0.0) * scale).min(255.0) as u8;
+    [
+        byte(c.x()),
+        byte(c.y()),
+        byte(c.z()),
+        (e + 128).clamp(0, 255) as u8,
+    ]
+}
+
+pub fn write_radiance_hdr<W: Write>(out: &mut W, img: &HdrImage) -> std::io::Result<()> {
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        img.height, img.width
+    )?;
+    let mut rgbe = vec![[0u8; 4]; img.width];
+    for j in 0..img.height {
+        for (i, pixel) in rgbe.iter_mut().enumerate() {
+            *pixel = color_to_rgbe(img.pixel(i, j));
+        }
+        write_rgbe_scanline(out, &rgbe)?;
+    }
+    out.flush()
+}
+
+fn write_rgbe_scanline<W: Write>(out: &mut W, rgbe: &[[u8; 4]]) -> std::io::Result<()> {
+    let width = rgbe.len();
+    if !(8..32768).contains(&width) {
+        for pixel in rgbe {
+            out.write_all(pixel)?;
+        }
+        return Ok(());
+    }
+
+    out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+    let mut component = vec![0u8; width];
+    let mut encoded = Vec::with_capacity(width + width / 64 + 2);
+    for c in 0..4 {
+        for (value, pixel) in component.iter_mut().zip(rgbe.iter()) {
+            *value = pixel[c];
+        }
+        encoded.clear();
+        // Runs of at least three equal bytes are stored as a count and a
+        // value, everything between them as literal spans.
+        let mut x = 0;
+        while x < width {
+            let mut run = 1;
+            while x + run < width && run < 127 && component[x + run] == component[x] {
+                run += 1;
+            }
+            if run >= 3 {
+                encoded.extend_from_slice(&[128 + run as u8, component[x]]);
+                x += run;
+                continue;
+            }
+            let start = x;
+            while x < width && x - start < 128 {
+                let repeats = x + 2 < width
+                    && component[x] == component[x + 1]
+                    && component[x] == component[x + 2];
+                if repeats {
+                    break;
+                }
+                x += 1;
+            }
+            encoded.push((x - start) as u8);
+            encoded.extend_from_slice(&component[start..x]);
+        }
+        out.write_all(&encoded)?;
+    }
+    Ok(())
+}
+
+// Three channel little-endian float map.
+pub fn write_pfm<W: Write>(out: &mut W, img: &HdrImage) -> std::io::Result<()> {
+    write!(out, "PF\n{} {}\n-1.0\n", img.width, img.height)?;
+    let mut bytes = Vec::with_capacity(img.width * img.height * 12);
+    for j in (0..img.height).rev() {
+        for i in 0..img.width {
+            let c = img.pixel(i, j);
+            for value in [c.x(), c.y(), c.z()].iter() {
+                bytes.extend_from_slice(&(*value as f32).to_le_bytes());
+            }
+        }
+    }
+    out.write_all(&bytes)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_pfm() {
+        let mut bytes = b"PF\n2 1\n-1.0\n".to_vec();
+        for f in [0.5_f32, 1.0, 2.0, 4.0, 8.0, 16.0].iter() {
+            bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        let img = read_pfm(&mut &bytes[..]).unwrap();
+        assert!(img.width == 2 && img.height == 1);
+        assert!(img.pixel(0, 0).x() == 0.5 && img.pixel(1, 0).z() == 16.0);
+    }
+
+    #[test]
+    fn test_read_radiance_hdr() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        // One run-length encoded scanline: every pixel is (1, 0.5, 0.25).
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        for value in [128u8, 64, 32, 129].iter() {
+            bytes.extend_from_slice(&[128 + 8, *value]);
+        }
+        let img = read_radiance_hdr(&mut &bytes[..]).unwrap();
+        assert!(img.width == 8 && img.height == 1);
+        let c = img.pixel(7, 0);
+        assert!((c.x() - 1.0).abs() < 0.01 && (c.y() - 0.5).abs() < 0.01);
+        assert!((c.z() - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let mut data = Vec::new();
+        for i in 0..40 {
+            let v = if i < 20 { 0.25 } else { i as f64 * 37.5 };
+            data.push(Color::new(v, 0.5 * v, 0.0));
+        }
+        let img = HdrImage {
+            width: 20,
+            height: 2,
+            data,
+        };
+
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, &img).unwrap();
+        let pfm = read_pfm(&mut &bytes[..]).unwrap();
+        assert!(pfm.pixel(19, 1).x() == 39.0 * 37.5 && pfm.pixel(0, 0).y() == 0.125);
+
+        let mut bytes = Vec::new();
+        write_radiance_hdr(&mut bytes, &img).unwrap();
+        let hdr = read_radiance_hdr(&mut &bytes[..]).unwrap();
+        for (a, b) in img.data.iter().zip(hdr.data.iter()) {
+            assert!((a - b).length() <= 0.01 * a.length());
+        }
+    }
+
+    #[test]
+    fn test_rgbe_saturates() {
+        assert!(color_to_rgbe(&Color::new(INFINITY, 1.0, 0.0)) == [255, 0, 0, 255]);
+        assert!(color_to_rgbe(&Color::new(1e300, f64::NAN, -INFINITY)) == [255, 0, 0, 255]);
+        assert!(color_to_rgbe(&Color::new(f64::NAN, f64::NAN, f64::NAN)) == [0, 0, 0, 0]);
+    }
+}
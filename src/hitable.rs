@@ -1,11 +1,13 @@
 use super::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HitRecord {
     pub p: Vec3,
     pub normal: Vec3,
-    pub mat_ptr: Rc<dyn Material>,
+    pub mat_ptr: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
 }
 
@@ -14,24 +16,16 @@ impl Default for HitRecord {
         Self {
             p: Default::default(),
             normal: Default::default(),
-            mat_ptr: Rc::new(UninitMaterial {}),
+            mat_ptr: Arc::new(UninitMaterial {}),
             t: Default::default(),
+            u: Default::default(),
+            v: Default::default(),
             front_face: Default::default(),
         }
     }
 }
 
 impl HitRecord {
-    pub fn new(p: Vec3, normal: Vec3, mat_ptr: Rc<dyn Material>, t: f64, front_face: bool) -> Self {
-        Self {
-            p,
-            normal,
-            mat_ptr,
-            t,
-            front_face,
-        }
-    }
-
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = dot(r.direction(), outward_normal) < 0.0;
         self.normal = if self.front_face {
@@ -46,10 +40,23 @@ impl HitRecord {
         self.normal = rec.normal.clone();
         self.mat_ptr = rec.mat_ptr.clone();
         self.t = rec.t;
+        self.u = rec.u;
+        self.v = rec.v;
         self.front_face = rec.front_face;
     }
 }
 
-pub trait Hitable {
+pub trait Hitable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool;
+
+    // Solid-angle density of random(origin) generating direction v. Only
+    // objects that can be sampled as lights override these.
+    fn pdf_value(&self, _origin: &Point3, _v: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn random(&self, _origin: &Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
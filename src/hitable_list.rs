@@ -1,8 +1,8 @@
 use super::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HitableList {
-    pub objects: Vec<Rc<dyn Hitable>>,
+    pub objects: Vec<Arc<dyn Hitable>>,
 }
 
 impl HitableList {
@@ -11,10 +11,7 @@ impl HitableList {
             objects: Vec::new(),
         }
     }
-    pub fn clear(&mut self) {
-        self.objects.clear();
-    }
-    pub fn add(&mut self, object: Rc<dyn Hitable>) {
+    pub fn add(&mut self, object: Arc<dyn Hitable>) {
         self.objects.push(object);
     }
 }
@@ -34,4 +31,40 @@ impl Hitable for HitableList {
         });
         hit_anything
     }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        if self.objects.is_empty() {
+            return false;
+        }
+
+        let mut temp_box: Aabb = Default::default();
+        let mut first_box = true;
+
+        for object in self.objects.iter() {
+            if !object.bounding_box(time0, time1, &mut temp_box) {
+                return false;
+            }
+            if first_box {
+                output_box.assign(&temp_box);
+            } else {
+                output_box.assign(&surrounding_box(output_box, &temp_box));
+            }
+            first_box = false;
+        }
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, v))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let n = self.objects.len();
+        let index = ((sample_1d() * n as f64) as usize).min(n - 1);
+        self.objects[index].random(origin)
+    }
 }
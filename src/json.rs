@@ -0,0 +1,303 @@
+use super::*;
+
+// A parsed JSON value, remembering the line it started on so that errors
+// found while interpreting a document can point back into the source.
+#[derive(Clone, Debug)]
+pub struct JsonValue {
+    pub line: usize,
+    pub kind: JsonKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum JsonKind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    // Members in source order.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn error(&self, msg: &str) -> String {
+        format!("line {}: {}", self.line, msg)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            JsonKind::Null => "null",
+            JsonKind::Bool(_) => "a boolean",
+            JsonKind::Number(_) => "a number",
+            JsonKind::String(_) => "a string",
+            JsonKind::Array(_) => "an array",
+            JsonKind::Object(_) => "an object",
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match &self.kind {
+            JsonKind::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64, String> {
+        match self.kind {
+            JsonKind::Number(n) => Ok(n),
+            _ => Err(self.error(&format!("expected a number, found {}", self.type_name()))),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, String> {
+        match self.kind {
+            JsonKind::Bool(b) => Ok(b),
+            _ => Err(self.error(&format!("expected a boolean, found {}", self.type_name()))),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&str, String> {
+        match &self.kind {
+            JsonKind::String(s) => Ok(s),
+            _ => Err(self.error(&format!("expected a string, found {}", self.type_name()))),
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&[JsonValue], String> {
+        match &self.kind {
+            JsonKind::Array(items) => Ok(items),
+            _ => Err(self.error(&format!("expected an array, found {}", self.type_name()))),
+        }
+    }
+
+    pub fn as_object(&self) -> Result<&[(String, JsonValue)], String> {
+        match &self.kind {
+            JsonKind::Object(members) => Ok(members),
+            _ => Err(self.error(&format!("expected an object, found {}", self.type_name()))),
+        }
+    }
+
+    // An [x, y, z] array.
+    pub fn as_vec3(&self) -> Result<Vec3, String> {
+        let items = self.as_array()?;
+        if items.len() != 3 {
+            return Err(self.error(&format!(
+                "expected an array of three numbers, found {} elements",
+                items.len()
+            )));
+        }
+        Ok(Vec3::new(
+            items[0].as_f64()?,
+            items[1].as_f64()?,
+            items[2].as_f64()?,
+        ))
+    }
+}
+
+pub fn parse_json(source: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected text after the end of the document"));
+    }
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl JsonParser {
+    fn error(&self, msg: &str) -> String {
+        format!("line {}: {}", self.line, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error(&format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(&format!("expected '{}', found end of file", expected))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        let line = self.line;
+        let kind = match self.peek() {
+            Some('{') => self.parse_object()?,
+            Some('[') => self.parse_array()?,
+            Some('"') => JsonKind::String(self.parse_string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number()?,
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.bump();
+                }
+                let word: String = self.chars[start..self.pos].iter().collect();
+                match word.as_str() {
+                    "true" => JsonKind::Bool(true),
+                    "false" => JsonKind::Bool(false),
+                    "null" => JsonKind::Null,
+                    _ => return Err(self.error(&format!("unexpected '{}'", word))),
+                }
+            }
+            Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            None => return Err(self.error("unexpected end of file")),
+        };
+        Ok(JsonValue { line, kind })
+    }
+
+    fn parse_object(&mut self) -> Result<JsonKind, String> {
+        self.expect('{')?;
+        let mut members: Vec<(String, JsonValue)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(JsonKind::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a quoted member name"));
+            }
+            let key_line = self.line;
+            let key = self.parse_string()?;
+            if members.iter().any(|(k, _)| *k == key) {
+                return Err(format!("line {}: duplicate key \"{}\"", key_line, key));
+            }
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonKind::Object(members)),
+                Some(c) => return Err(self.error(&format!("expected ',' or '}}', found '{}'", c))),
+                None => return Err(self.error("unterminated object")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonKind, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(JsonKind::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonKind::Array(items)),
+                Some(c) => return Err(self.error(&format!("expected ',' or ']', found '{}'", c))),
+                None => return Err(self.error("unterminated array")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid \\u escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape in string")),
+                    };
+                    s.push(escaped);
+                }
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonKind, String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.bump();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(JsonKind::Number(n)),
+            _ => Err(self.error(&format!("invalid number '{}'", text))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let doc =
+            parse_json("{\n  \"a\": [1, -2.5e1, true],\n  \"b\": {\"c\": \"x\\ny\"}\n}").unwrap();
+        let a = doc.get("a").unwrap();
+        assert!(a.line == 2 && a.as_array().unwrap()[1].as_f64().unwrap() == -25.0);
+        assert!(doc.get("b").unwrap().get("c").unwrap().as_str().unwrap() == "x\ny");
+
+        let e = parse_json("{\n  \"a\": 1,\n  \"b\": [1 2]\n}")
+            .err()
+            .unwrap();
+        assert!(e == "line 3: expected ',' or ']', found '2'");
+        let e = parse_json("{\"a\": 1, \"a\": 2}").err().unwrap();
+        assert!(e == "line 1: duplicate key \"a\"");
+    }
+}
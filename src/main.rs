@@ -1,55 +1,225 @@
+mod aabb;
+mod aarect;
+mod adaptive;
+mod background;
+mod bvh;
 mod camera;
+mod checkpoint;
+mod cli;
 mod color;
+mod constant_medium;
+mod cuboid;
+mod environment;
+mod framebuffer;
+mod hdr;
 mod hitable;
 mod hitable_list;
+mod json;
 mod material;
+mod moving_sphere;
+mod obj;
+mod onb;
+mod output;
+mod pdf;
+mod perlin;
+mod punctual;
+mod quad;
 mod ray;
+mod render;
 mod rtweekend;
+mod sampler;
+mod scene_file;
+mod sky;
 mod sphere;
+mod texture;
+mod tonemap;
+mod transform;
+mod triangle;
+mod triangle_mesh;
 mod vec3;
 
+use aabb::*;
+use aarect::*;
+use adaptive::*;
+use background::*;
+use bvh::*;
 use camera::*;
+use checkpoint::*;
+use cli::*;
 use color::*;
+use constant_medium::*;
+use cuboid::*;
+use environment::*;
+use framebuffer::*;
+use hdr::*;
 use hitable::*;
 use hitable_list::*;
+use json::*;
 use material::*;
+use moving_sphere::*;
+use obj::*;
+use onb::*;
+use output::*;
+use pdf::*;
+use perlin::*;
+use punctual::*;
+use quad::*;
 use rand::Rng;
 use ray::*;
+use render::*;
 use rtweekend::*;
+use sampler::*;
+use scene_file::*;
+use sky::*;
 use sphere::*;
+use std::collections::HashMap;
 use std::io::Write;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+use texture::*;
+use tonemap::*;
+use transform::*;
+use triangle::*;
+use triangle_mesh::*;
 use vec3::*;
 
-fn ray_color(r: &Ray, world: &dyn Hitable, depth: i64) -> Color {
+fn ray_color(
+    r: &Ray,
+    background: &dyn Background,
+    world: &dyn Hitable,
+    lights: &HitableList,
+    punctual_lights: &[Arc<dyn PunctualLight>],
+    depth: i64,
+) -> Color {
+    trace(r, background, world, lights, punctual_lights, depth, None)
+}
+
+// bsdf_pdf is the density with which the previous bounce sampled r, when
+// that bounce also sampled the lights directly. Emission found by r is
+// then MIS-weighted against the light sample so it is not counted twice.
+fn trace(
+    r: &Ray,
+    background: &dyn Background,
+    world: &dyn Hitable,
+    lights: &HitableList,
+    punctual_lights: &[Arc<dyn PunctualLight>],
+    depth: i64,
+    bsdf_pdf: Option<f64>,
+) -> Color {
     let mut rec: HitRecord = Default::default();
     // If we've exceeded the ray bounce limit, no more light is gathered.
     if depth <= 0 {
         return Color::new(0.0, 0.0, 0.0);
     }
 
-    if world.hit(r, 0.001, INFINITY, &mut rec) {
-        let mut scattered: Ray = Default::default();
-        let mut attenuation: Color = Default::default();
-        if rec
-            .mat_ptr
-            .scatter(r, &rec, &mut attenuation, &mut scattered)
-        {
-            return &attenuation * &ray_color(&scattered, world, depth - 1);
+    let mis_weight = |bsdf_pdf: Option<f64>| match bsdf_pdf {
+        Some(bsdf_pdf) => {
+            let light_pdf = LightPdf::new(lights, background, r.origin());
+            power_heuristic(bsdf_pdf, light_pdf.value(r.direction()))
         }
-        return Color::new(0.0, 0.0, 0.0);
+        None => 1.0,
+    };
+
+    // If the ray hits nothing, return the background color.
+    if !world.hit(r, 0.001, INFINITY, &mut rec) {
+        return mis_weight(bsdf_pdf) * &background.value(r);
+    }
+
+    let mut srec: ScatterRecord = Default::default();
+    let emitted = mis_weight(bsdf_pdf) * &rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+
+    if !rec.mat_ptr.scatter(r, &rec, &mut srec) {
+        return emitted;
     }
 
-    let unit_direction = unit_vector(r.direction());
-    let t = 0.5 * (unit_direction.y() + 1.0);
-    &((1.0 - t) * &Color::new(1.0, 1.0, 1.0)) + &(t * &Color::new(0.5, 0.7, 1.0))
+    if srec.is_specular {
+        return &emitted
+            + &(&srec.attenuation
+                * &trace(
+                    &srec.specular_ray,
+                    background,
+                    world,
+                    lights,
+                    punctual_lights,
+                    depth - 1,
+                    None,
+                ));
+    }
+
+    let pdf = match &srec.pdf_ptr {
+        Some(pdf) => pdf,
+        None => return emitted,
+    };
+
+    // Next-event estimation: sample a direction toward the lights and add
+    // the light arriving along it directly, unless something blocks the way.
+    let light_pdf = LightPdf::new(lights, background, &rec.p);
+    let sample_lights = !light_pdf.is_empty();
+    let mut direct = Color::new(0.0, 0.0, 0.0);
+    if sample_lights {
+        let shadow = Ray::new(rec.p.clone(), light_pdf.generate(), r.time());
+        let light_pdf_val = light_pdf.value(shadow.direction());
+        let mut light_rec: HitRecord = Default::default();
+        if light_pdf_val > 0.0 {
+            let light_emitted = if world.hit(&shadow, 0.001, INFINITY, &mut light_rec) {
+                light_rec
+                    .mat_ptr
+                    .emitted(light_rec.u, light_rec.v, &light_rec.p)
+            } else {
+                background.value(&shadow)
+            };
+            let scattering_pdf = rec.mat_ptr.scattering_pdf(r, &rec, &shadow);
+            let weight = power_heuristic(light_pdf_val, pdf.value(shadow.direction()));
+            direct =
+                (weight * scattering_pdf / light_pdf_val) * &(&srec.attenuation * &light_emitted);
+        }
+    }
+
+    // Punctual lights can only be reached by a shadow ray. scattering_pdf
+    // already includes the cosine term, so it scales the irradiance directly.
+    let mut light_sample: LightSample = Default::default();
+    for light in punctual_lights {
+        if !light.sample(&rec.p, &mut light_sample) {
+            continue;
+        }
+        let shadow = Ray::new(rec.p.clone(), light_sample.direction.clone(), r.time());
+        let mut shadow_rec: HitRecord = Default::default();
+        if world.hit(
+            &shadow,
+            0.001,
+            light_sample.distance - 0.001,
+            &mut shadow_rec,
+        ) {
+            continue;
+        }
+        let scattering_pdf = rec.mat_ptr.scattering_pdf(r, &rec, &shadow);
+        direct += scattering_pdf * &(&srec.attenuation * &light_sample.irradiance);
+    }
+
+    let scattered = Ray::new(rec.p.clone(), pdf.generate(), r.time());
+    let pdf_val = pdf.value(scattered.direction());
+    let scattering_pdf = rec.mat_ptr.scattering_pdf(r, &rec, &scattered);
+
+    let sample_color = trace(
+        &scattered,
+        background,
+        world,
+        lights,
+        punctual_lights,
+        depth - 1,
+        if sample_lights { Some(pdf_val) } else { None },
+    );
+    &(&emitted + &direct) + &(&(scattering_pdf * &(&srec.attenuation * &sample_color)) / pdf_val)
 }
 
+// The default scene: the cover of Ray Tracing in One Weekend, with the
+// diffuse spheres bouncing upward while the shutter is open from time 0 to 1,
+// as in The Next Week, so that it renders with motion blur.
 fn random_scene() -> HitableList {
     let mut world = HitableList::new();
-    //let ground_material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-    let ground_material = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Rc::new(Sphere::new(
+    //let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_material = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(Sphere::new(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
         ground_material,
@@ -68,39 +238,47 @@ fn random_scene() -> HitableList {
                 if choose_mat < 0.8 {
                     // diffuse
                     let albedo = &Color::random() * &Color::random();
-                    let sphere_material = Rc::new(Lambertian::new(albedo));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
+                    let sphere_material = Arc::new(Lambertian::new(albedo));
+                    let center2 = &center + &Vec3(0.0, random_double_minmax(0.0, 0.5), 0.0);
+                    world.add(Arc::new(MovingSphere::new(
+                        center,
+                        center2,
+                        0.0,
+                        1.0,
+                        0.2,
+                        sphere_material,
+                    )));
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = Color::random_minmax(0.5, 1.0);
                     let fuzz = random_double_minmax(0.0, 0.5);
-                    let sphere_material = Rc::new(Metal::new(albedo, fuzz));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
+                    let sphere_material = Arc::new(Metal::new(albedo, fuzz));
+                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
                 } else {
                     // glass
-                    let sphere_material = Rc::new(Dielectric::new(1.5));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
+                    let sphere_material = Arc::new(Dielectric::new(1.5));
+                    world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
                 }
             }
         }
     }
 
-    let material1 = Rc::new(Dielectric::new(1.5));
-    world.add(Rc::new(Sphere::new(
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Sphere::new(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
         material1,
     )));
 
-    let material2 = Rc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    world.add(Rc::new(Sphere::new(
+    let material2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(Sphere::new(
         Point3::new(-4.0, 1.0, 0.0),
         1.0,
         material2,
     )));
 
-    let material3 = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Rc::new(Sphere::new(
+    let material3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(Sphere::new(
         Point3::new(4.0, 1.0, 0.0),
         1.0,
         material3,
@@ -109,6 +287,286 @@ fn random_scene() -> HitableList {
     world
 }
 
+fn two_perlin_spheres() -> HitableList {
+    let mut objects = HitableList::new();
+
+    let pertext = Arc::new(MarbleTexture::new(4.0, Color::new(1.0, 1.0, 1.0)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::from_texture(pertext.clone())),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(Lambertian::from_texture(pertext)),
+    )));
+
+    objects
+}
+
+fn simple_light() -> HitableList {
+    let mut objects = two_perlin_spheres();
+
+    let difflight = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 7.0, 0.0),
+        2.0,
+        difflight,
+    )));
+
+    objects
+}
+
+fn cornell_box() -> HitableList {
+    let mut objects = HitableList::new();
+
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    objects.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    objects.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    objects.add(Arc::new(XzRect::new(
+        213.0, 343.0, 227.0, 332.0, 554.0, light,
+    )));
+    objects.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    objects.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    objects.add(Arc::new(XyRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    let box1 = Arc::new(Cuboid::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    objects.add(Arc::new(
+        Transformed::new(
+            box1,
+            &Transform::translate(&Vec3(265.0, 0.0, 295.0)) * &Transform::rotate_y(15.0),
+        )
+        .expect("rotation is invertible"),
+    ));
+
+    let box2 = Arc::new(Cuboid::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    objects.add(Arc::new(
+        Transformed::new(
+            box2,
+            &Transform::translate(&Vec3(130.0, 0.0, 65.0)) * &Transform::rotate_y(-18.0),
+        )
+        .expect("rotation is invertible"),
+    ));
+
+    objects
+}
+
+fn cornell_smoke() -> HitableList {
+    let mut objects = HitableList::new();
+
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::new(7.0, 7.0, 7.0)));
+
+    objects.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    objects.add(Arc::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    objects.add(Arc::new(XzRect::new(
+        113.0, 443.0, 127.0, 432.0, 554.0, light,
+    )));
+    objects.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    objects.add(Arc::new(XzRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    objects.add(Arc::new(XyRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    let box1 = Arc::new(Cuboid::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    let box1 = Arc::new(
+        Transformed::new(
+            box1,
+            &Transform::translate(&Vec3(265.0, 0.0, 295.0)) * &Transform::rotate_y(15.0),
+        )
+        .expect("rotation is invertible"),
+    );
+    objects.add(Arc::new(ConstantMedium::new(
+        box1,
+        0.01,
+        Color::new(0.0, 0.0, 0.0),
+    )));
+
+    let box2 = Arc::new(Cuboid::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    let box2 = Arc::new(
+        Transformed::new(
+            box2,
+            &Transform::translate(&Vec3(130.0, 0.0, 65.0)) * &Transform::rotate_y(-18.0),
+        )
+        .expect("rotation is invertible"),
+    );
+    objects.add(Arc::new(ConstantMedium::new(
+        box2,
+        0.01,
+        Color::new(1.0, 1.0, 1.0),
+    )));
+
+    objects
+}
+
+fn lookdev_scene() -> HitableList {
+    let mut objects = HitableList::new();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground,
+    )));
+
+    let diffuse = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        diffuse,
+    )));
+    let glass = Arc::new(Dielectric::new(1.5));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        glass,
+    )));
+    let chrome = Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        chrome,
+    )));
+
+    objects
+}
+
+fn punctual_scene() -> HitableList {
+    let mut objects = HitableList::new();
+
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    objects.add(Arc::new(Quad::new(
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        white.clone(),
+    )));
+    objects.add(Arc::new(Quad::new(
+        Point3::new(-10.0, 0.0, -4.0),
+        Vec3::new(0.0, 8.0, 0.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        white.clone(),
+    )));
+
+    objects.add(Arc::new(Cuboid::new(
+        &Point3::new(-2.5, 0.0, -1.0),
+        &Point3::new(-0.5, 2.5, 1.0),
+        white,
+    )));
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    objects.add(Arc::new(Sphere::new(Point3::new(1.5, 1.0, 0.0), 1.0, red)));
+    let metal = Arc::new(Metal::new(Color::new(0.8, 0.85, 0.88), 0.2));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(3.5, 0.7, 1.5),
+        0.7,
+        metal,
+    )));
+
+    objects
+}
+
+// Found from the source tree, so that the scene runs from any directory.
+const OBJ_SCENE_MESH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/icosahedron.obj");
+
+fn obj_scene() -> std::io::Result<HitableList> {
+    let mut objects = HitableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1001.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::from_texture(checker)),
+    )));
+
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    materials.insert(
+        "body".to_string(),
+        Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))),
+    );
+    materials.insert(
+        "accent".to_string(),
+        Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 0.1)),
+    );
+    let default_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    objects.add(load_obj(OBJ_SCENE_MESH, &materials, default_material)?);
+
+    Ok(objects)
+}
+
+// Reports a bad command line or scene file and exits, flushing err first
+// since exiting skips destructors.
+fn exit_with_error<W: Write>(err: &mut W, msg: &str, code: i32) -> ! {
+    let _ = writeln!(err, "error: {}", msg);
+    let _ = err.flush();
+    std::process::exit(code);
+}
+
 fn main() -> std::io::Result<()> {
     let out = std::io::stdout();
     let mut out = std::io::BufWriter::new(out.lock());
@@ -116,54 +574,388 @@ fn main() -> std::io::Result<()> {
     let err = std::io::stderr();
     let mut err = std::io::BufWriter::new(err.lock());
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut settings = match parse_args(args.clone()) {
+        Ok(Some(settings)) => settings,
+        Ok(None) => {
+            write!(out, "{}", USAGE)?;
+            return Ok(());
+        }
+        Err(msg) => exit_with_error(&mut err, &format!("{}\nRun with --help for usage.", msg), 2),
+    };
+
+    // A resumed render continues with its checkpoint's seed. Otherwise,
+    // without --seed, pick one and report it so the image can be reproduced.
+    let checkpoint = match &settings.resume {
+        Some(filename) => match load_checkpoint(filename) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => exit_with_error(&mut err, &e.to_string(), 1),
+        },
+        None => None,
+    };
+    let seed = match (settings.seed, &checkpoint) {
+        (Some(seed), Some(checkpoint)) if seed != checkpoint.seed => exit_with_error(
+            &mut err,
+            &format!(
+                "--seed {} does not match the checkpoint's seed {}",
+                seed, checkpoint.seed
+            ),
+            2,
+        ),
+        (Some(seed), _) => seed,
+        (None, Some(checkpoint)) => checkpoint.seed,
+        (None, None) => rand::thread_rng().gen(),
+    };
+    writeln!(err, "Seed: {}", seed)?;
+    seed_random(seed);
+
+    // A scene file's render settings take the place of the built-in
+    // defaults, so the command line is parsed again on top of them.
+    let scene_file = match &settings.scene_file {
+        Some(filename) => match load_scene(filename) {
+            Ok(scene) => Some(scene),
+            Err(e) => exit_with_error(&mut err, &e.to_string(), 1),
+        },
+        None => None,
+    };
+    if let Some(scene) = &scene_file {
+        settings = match parse_args_with(args, scene.settings()) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => unreachable!("help is handled by the first parse"),
+            Err(msg) => {
+                exit_with_error(&mut err, &format!("{}\nRun with --help for usage.", msg), 2)
+            }
+        };
+    }
+
+    // Fail now, rather than after the render, if an image or the checkpoint
+    // cannot be written.
+    let outputs = [
+        Some(&settings.output),
+        settings.sample_map.as_ref(),
+        settings.checkpoint.as_ref(),
+    ];
+    for filename in outputs.iter().flatten().filter(|f| !f.is_empty()) {
+        if let Err(e) = check_writable(filename) {
+            exit_with_error(&mut err, &e.to_string(), 1);
+        }
+    }
+
     // Image
-    const ASPECT_RATIO: f64 = 16.0 / 9.0;
-    const IMAGE_WIDTH: i64 = 1200;
-    const IMAGE_HEIGHT: i64 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as i64;
-    const SAMPLES_PER_PIXEL: i64 = 500;
-    const MAX_DEPTH: i64 = 50;
+    let image_width = settings.image_width;
+    let image_height = settings.image_height();
+    let samples_per_pixel = settings.samples_per_pixel;
+    let max_depth = settings.max_depth;
 
     // World
-    let world = random_scene();
+    let objects;
+    let mut lights = HitableList::new();
+    let mut punctual_lights: Vec<Arc<dyn PunctualLight>> = Vec::new();
+    let background: Box<dyn Background>;
+    let lookfrom;
+    let lookat;
+    let vfov;
+    let mut aperture = 0.0;
+    let mut vup = Vec3(0.0, 1.0, 0.0);
+    let mut focus_dist = 10.0;
+    // Files the scene was loaded from.
+    let mut files = Vec::new();
 
-    // Camera
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3(0.0, 1.0, 0.0);
-    let dist_to_focus: f64 = 10.0;
-    let aperture: f64 = 0.1;
+    if let Some(scene) = scene_file {
+        files.extend(settings.scene_file.clone());
+        files.extend(scene.files);
+        objects = scene.objects;
+        lights = scene.lights;
+        punctual_lights = scene.punctual_lights;
+        background = scene.background;
+        lookfrom = scene.camera.lookfrom;
+        lookat = scene.camera.lookat;
+        vfov = scene.camera.vfov;
+        aperture = scene.camera.aperture;
+        vup = scene.camera.vup;
+        focus_dist = scene.camera.focus_dist;
+    } else {
+        match settings.scene {
+            2 => {
+                objects = two_perlin_spheres();
+                background = Box::new(GradientBackground::default());
+                lookfrom = Point3::new(13.0, 2.0, 3.0);
+                lookat = Point3::new(0.0, 0.0, 0.0);
+                vfov = 20.0;
+            }
+            3 => {
+                objects = simple_light();
+                lights.add(Arc::new(Sphere::new(
+                    Point3::new(0.0, 7.0, 0.0),
+                    2.0,
+                    Arc::new(UninitMaterial {}),
+                )));
+                background = Box::new(SolidBackground::new(Color::new(0.0, 0.0, 0.0)));
+                lookfrom = Point3::new(26.0, 3.0, 6.0);
+                lookat = Point3::new(0.0, 2.0, 0.0);
+                vfov = 20.0;
+            }
+            4 => {
+                objects = cornell_box();
+                lights.add(Arc::new(XzRect::new(
+                    213.0,
+                    343.0,
+                    227.0,
+                    332.0,
+                    554.0,
+                    Arc::new(UninitMaterial {}),
+                )));
+                background = Box::new(SolidBackground::new(Color::new(0.0, 0.0, 0.0)));
+                lookfrom = Point3::new(278.0, 278.0, -800.0);
+                lookat = Point3::new(278.0, 278.0, 0.0);
+                vfov = 40.0;
+            }
+            5 => {
+                objects = cornell_smoke();
+                lights.add(Arc::new(XzRect::new(
+                    113.0,
+                    443.0,
+                    127.0,
+                    432.0,
+                    554.0,
+                    Arc::new(UninitMaterial {}),
+                )));
+                background = Box::new(SolidBackground::new(Color::new(0.0, 0.0, 0.0)));
+                lookfrom = Point3::new(278.0, 278.0, -800.0);
+                lookat = Point3::new(278.0, 278.0, 0.0);
+                vfov = 40.0;
+            }
+            6 => {
+                objects = match obj_scene() {
+                    Ok(objects) => objects,
+                    Err(e) => exit_with_error(&mut err, &e.to_string(), 1),
+                };
+                files.push(OBJ_SCENE_MESH.to_string());
+                background = Box::new(GradientBackground::default());
+                lookfrom = Point3::new(0.0, 1.0, 6.0);
+                lookat = Point3::new(0.0, 0.0, 0.0);
+                vfov = 30.0;
+            }
+            7 => {
+                objects = lookdev_scene();
+                background = match EnvironmentMap::load(
+                    &settings.env_map,
+                    settings.env_rotation,
+                    settings.env_intensity,
+                ) {
+                    Ok(map) => Box::new(map),
+                    Err(e) => exit_with_error(&mut err, &e.to_string(), 1),
+                };
+                files.push(settings.env_map.clone());
+                lookfrom = Point3::new(0.0, 2.0, 10.0);
+                lookat = Point3::new(0.0, 1.0, 0.0);
+                vfov = 30.0;
+            }
+            8 => {
+                objects = lookdev_scene();
+                background = Box::new(SunSky::new(
+                    settings.sun_elevation,
+                    settings.sun_azimuth,
+                    settings.turbidity,
+                    settings.sky_intensity,
+                ));
+                lookfrom = Point3::new(0.0, 2.0, 10.0);
+                lookat = Point3::new(0.0, 1.0, 0.0);
+                vfov = 30.0;
+            }
+            9 => {
+                objects = punctual_scene();
+                punctual_lights.push(Arc::new(PointLight::new(
+                    Point3::new(-3.0, 3.0, 2.0),
+                    Color::new(12.0, 10.0, 8.0),
+                )));
+                punctual_lights.push(Arc::new(SpotLight::new(
+                    Point3::new(3.0, 5.0, 1.0),
+                    Point3::new(1.5, 0.0, 0.0),
+                    Color::new(30.0, 30.0, 40.0),
+                    25.0,
+                    15.0,
+                )));
+                punctual_lights.push(Arc::new(DirectionalLight::new(
+                    Vec3::new(-1.0, -2.0, -1.0),
+                    Color::new(0.3, 0.3, 0.3),
+                )));
+                background = Box::new(SolidBackground::new(Color::new(0.02, 0.02, 0.03)));
+                lookfrom = Point3::new(0.0, 4.0, 12.0);
+                lookat = Point3::new(0.0, 1.0, 0.0);
+                vfov = 35.0;
+            }
+            _ => {
+                objects = random_scene();
+                background = Box::new(GradientBackground::default());
+                lookfrom = Point3::new(13.0, 2.0, 3.0);
+                lookat = Point3::new(0.0, 0.0, 0.0);
+                vfov = 20.0;
+                aperture = 0.1;
+            }
+        }
+    }
+    let world = build_bvh(&objects, 0.0, 1.0);
 
+    // Camera
     let cam = Camera::new(
-        lookfrom,
-        lookat,
+        settings.lookfrom.clone().unwrap_or(lookfrom),
+        settings.lookat.clone().unwrap_or(lookat),
         vup,
-        20.0,
-        ASPECT_RATIO,
-        aperture,
-        dist_to_focus,
-        0.0,
+        settings.vfov.unwrap_or(vfov),
+        settings.aspect_ratio,
+        settings.aperture.unwrap_or(aperture),
+        settings.focus_dist.unwrap_or(focus_dist),
         0.0,
+        1.0,
     );
 
     // Render
-    writeln!(out, "P3")?;
-    writeln!(out, "{} {}", IMAGE_WIDTH, IMAGE_HEIGHT)?;
-    writeln!(out, "255")?;
-
-    for j in (0..IMAGE_HEIGHT).rev() {
-        writeln!(err, "Scanlines remaining: {} ", j)?;
-        err.flush()?;
-        for i in 0..IMAGE_WIDTH {
-            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-            for s in 0..SAMPLES_PER_PIXEL {
-                let u = (i as f64 + random_double()) / (IMAGE_WIDTH as f64 - 1.0);
-                let v = (j as f64 + random_double()) / (IMAGE_HEIGHT as f64 - 1.0);
-                let r = cam.get_ray(u, v);
-                pixel_color += ray_color(&r, &world, MAX_DEPTH);
+    let thread_count = match settings.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    // The contents of the files the scene was loaded from are part of the
+    // settings too.
+    let description = settings.description()
+        + &fingerprint_files(&files)
+            .unwrap_or_else(|e| exit_with_error(&mut err, &e.to_string(), 1));
+    let (mut framebuffer, mut stats, completed) = match checkpoint {
+        Some(checkpoint) => {
+            if checkpoint.settings != description {
+                exit_with_error(
+                    &mut err,
+                    &format!(
+                        "{} was saved by a render with different settings\n  \
+                         checkpoint: {}\n  this run:   {}",
+                        settings.resume.as_deref().unwrap_or_default(),
+                        checkpoint.settings,
+                        description
+                    ),
+                    1,
+                );
+            }
+            writeln!(
+                err,
+                "Resuming at {} samples per pixel",
+                checkpoint.completed
+            )?;
+            (
+                checkpoint.framebuffer,
+                checkpoint.stats,
+                checkpoint.completed,
+            )
+        }
+        None => {
+            let framebuffer = Framebuffer::new(image_width as usize, image_height as usize);
+            let stats = vec![PixelStats::default(); framebuffer.samples.len()];
+            (framebuffer, stats, 0)
+        }
+    };
+    let renderer = Renderer {
+        world: &world,
+        camera: &cam,
+        background: background.as_ref(),
+        lights: &lights,
+        punctual_lights: &punctual_lights,
+        sampler: new_sampler(settings.sampler, seed, samples_per_pixel as u64),
+        adaptive: settings
+            .adaptive_threshold
+            .map(|threshold| AdaptiveSampling::new(threshold, settings.min_samples_per_pixel)),
+        seed,
+        max_depth,
+        thread_count,
+    };
+    let tone_mapper = ToneMapper::new(settings.tone_map, settings.exposure);
+
+    // A progressive render rewrites the output after each pass, once the
+    // snapshot interval has passed, so it can be inspected or stopped early.
+    let mut passes = match settings.progressive {
+        Some(_) => progressive_passes(samples_per_pixel),
+        None => vec![(0, samples_per_pixel)],
+    };
+    if settings.checkpoint.is_some() {
+        let max_samples = (samples_per_pixel + CHECKPOINT_PASSES - 1) / CHECKPOINT_PASSES;
+        passes = split_passes(&passes, max_samples);
+    }
+    // Skip the samples a resumed render already has. The image does not
+    // depend on how samples are split into passes, so this gives the same
+    // result as a render that was never interrupted.
+    let passes: Vec<(i64, i64)> = passes
+        .into_iter()
+        .filter(|&(_, end)| end > completed)
+        .map(|(first, end)| (first.max(completed), end))
+        .collect();
+    let mut last_snapshot = Instant::now();
+    let mut last_checkpoint = Instant::now();
+    for (pass, &(first_sample, end_sample)) in passes.iter().enumerate() {
+        if passes.len() > 1 {
+            writeln!(
+                err,
+                "Pass {} of {}: {} samples per pixel",
+                pass + 1,
+                passes.len(),
+                end_sample
+            )?;
+        }
+        renderer.render_pass(
+            &mut framebuffer,
+            &mut stats,
+            first_sample,
+            end_sample,
+            &mut err,
+        )?;
+
+        let is_last = pass + 1 == passes.len();
+        if let Some(interval) = settings.progressive {
+            if !is_last && last_snapshot.elapsed().as_secs_f64() >= interval {
+                write_image(&settings.output, &framebuffer, &tone_mapper)
+                    .unwrap_or_else(|e| exit_with_error(&mut err, &e.to_string(), 1));
+                writeln!(err, "Wrote snapshot to {}", settings.output)?;
+                last_snapshot = Instant::now();
+            }
+        }
+        if let Some(filename) = &settings.checkpoint {
+            if !is_last && last_checkpoint.elapsed().as_secs_f64() >= settings.checkpoint_interval {
+                save_checkpoint(
+                    filename,
+                    &description,
+                    seed,
+                    end_sample,
+                    &framebuffer,
+                    &stats,
+                )
+                .unwrap_or_else(|e| exit_with_error(&mut err, &e.to_string(), 1));
+                writeln!(err, "Saved checkpoint to {}", filename)?;
+                last_checkpoint = Instant::now();
             }
-            write_color(&mut out, pixel_color, SAMPLES_PER_PIXEL)?;
         }
     }
+
+    if settings.output.is_empty() {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", image_width, image_height)?;
+        writeln!(out, "255")?;
+        for j in 0..framebuffer.height {
+            for i in 0..framebuffer.width {
+                write_color(&mut out, &framebuffer.pixel(i, j), &tone_mapper)?;
+            }
+        }
+    } else {
+        write_image(&settings.output, &framebuffer, &tone_mapper)
+            .unwrap_or_else(|e| exit_with_error(&mut err, &e.to_string(), 1));
+    }
+    if renderer.adaptive.is_some() {
+        let total: i64 = framebuffer.samples.iter().sum();
+        let average = total as f64 / framebuffer.samples.len() as f64;
+        writeln!(err, "Average samples per pixel: {:.1}", average)?;
+    }
+    if let Some(filename) = &settings.sample_map {
+        let map = framebuffer.sample_map(samples_per_pixel);
+        write_image(filename, &map, &Default::default())
+            .unwrap_or_else(|e| exit_with_error(&mut err, &e.to_string(), 1));
+    }
     writeln!(err, "Done.")?;
     Ok(())
 }
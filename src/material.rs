@@ -1,4 +1,5 @@
 use super::*;
+use std::sync::Arc;
 
 pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
@@ -6,49 +7,84 @@ pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
-pub trait Material {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool;
+// How a material scatters an incoming ray. Specular materials pick the
+// outgoing ray themselves; all others supply a pdf for the integrator to
+// sample and weight by scattering_pdf / pdf.
+pub struct ScatterRecord {
+    pub specular_ray: Ray,
+    pub is_specular: bool,
+    pub attenuation: Color,
+    pub pdf_ptr: Option<Arc<dyn Pdf>>,
+}
+
+impl Default for ScatterRecord {
+    fn default() -> Self {
+        Self {
+            specular_ray: Default::default(),
+            is_specular: false,
+            attenuation: Color::new(1.0, 1.0, 1.0),
+            pdf_ptr: None,
+        }
+    }
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
+        false
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
 }
 
 pub struct Lambertian {
-    pub albedo: Vec3,
+    pub albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Vec3) -> Self {
+    pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
         Lambertian { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        let scatter_direction = &rec.normal + &random_unit_vector();
-        let new_scattered = Ray::new(rec.p.clone(), scatter_direction, Default::default());
-        scattered.assign(&new_scattered);
-        attenuation.assign(&self.albedo);
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.is_specular = false;
+        srec.attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        srec.pdf_ptr = Some(Arc::new(CosinePdf::new(&rec.normal)));
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = dot(&rec.normal, &unit_vector(scattered.direction()));
+        if cosine < 0.0 {
+            0.0
+        } else {
+            cosine / PI
+        }
+    }
 }
 
 pub struct Metal {
-    pub albedo: Vec3,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
 impl Metal {
-    pub fn new(albedo: Vec3, fuzz: f64) -> Self {
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
         Metal {
             albedo,
             fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
@@ -57,22 +93,17 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
         let reflected = reflect(&unit_vector(r_in.direction()), &rec.normal);
-        let new_scattered = Ray::new(
+        srec.specular_ray = Ray::new(
             rec.p.clone(),
             &reflected + &(self.fuzz * &random_in_unit_sphere()),
-            Default::default(),
+            r_in.time(),
         );
-        scattered.assign(&new_scattered);
-        attenuation.assign(&self.albedo);
-        dot(scattered.direction(), &rec.normal) > 0.0
+        srec.is_specular = true;
+        srec.attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        srec.pdf_ptr = None;
+        dot(srec.specular_ray.direction(), &rec.normal) > 0.0
     }
 }
 
@@ -87,14 +118,10 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        attenuation.assign(&Color::new(1.0, 1.0, 1.0));
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.is_specular = true;
+        srec.pdf_ptr = None;
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
         let etai_over_etat = if rec.front_face {
             1.0 / self.ref_idx
         } else {
@@ -105,28 +132,61 @@ impl Material for Dielectric {
         let cos_theta = dot(&-&unit_direction, &rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        if (etai_over_etat * sin_theta > 1.0)
-            || (random_double() < schlick(cos_theta, etai_over_etat))
+        if (etai_over_etat * sin_theta > 1.0) || (sample_1d() < schlick(cos_theta, etai_over_etat))
         {
             let reflected = reflect(&unit_direction, &rec.normal);
-            scattered.assign(&Ray::new(rec.p.clone(), reflected, Default::default()));
+            srec.specular_ray = Ray::new(rec.p.clone(), reflected, r_in.time());
             return true;
         }
         let refracted = refract(&unit_direction, &rec.normal, etai_over_etat);
-        scattered.assign(&Ray::new(rec.p.clone(), refracted, Default::default()));
+        srec.specular_ray = Ray::new(rec.p.clone(), refracted, r_in.time());
         true
     }
 }
 
-pub struct UninitMaterial {}
-impl Material for UninitMaterial {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        false
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(c: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(c)))
+    }
+
+    pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
+        Self { emit }
     }
 }
+
+impl Material for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.emit.value(u, v, p)
+    }
+}
+
+// Scatters uniformly in all directions; the phase function of a ConstantMedium.
+pub struct Isotropic {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.is_specular = false;
+        srec.attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        srec.pdf_ptr = Some(Arc::new(SpherePdf {}));
+        true
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+pub struct UninitMaterial {}
+impl Material for UninitMaterial {}
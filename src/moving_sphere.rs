@@ -0,0 +1,116 @@
+use super::*;
+use std::sync::Arc;
+
+pub struct MovingSphere {
+    pub center0: Point3,
+    pub center1: Point3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub mat_ptr: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            mat_ptr,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 == self.time0 {
+            return self.center0.clone();
+        }
+        &self.center0
+            + &(((time - self.time0) / (self.time1 - self.time0))
+                * &(&self.center1 - &self.center0))
+    }
+}
+
+impl Hitable for MovingSphere {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let center = self.center(r.time());
+        let oc = r.origin() - &center;
+        let a = r.direction().length_squared();
+        let half_b = dot(&oc, r.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant > 0.0 {
+            let root = discriminant.sqrt();
+
+            let temp = (-half_b - root) / a;
+            if temp < t_max && temp > t_min {
+                rec.t = temp;
+                rec.p = r.at(rec.t);
+                let outward_normal = &(&rec.p - &center) / self.radius;
+                rec.set_face_normal(r, &outward_normal);
+                get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
+                rec.mat_ptr = self.mat_ptr.clone();
+                return true;
+            }
+            let temp = (-half_b + root) / a;
+            if temp < t_max && temp > t_min {
+                rec.t = temp;
+                rec.p = r.at(rec.t);
+                let outward_normal = &(&rec.p - &center) / self.radius;
+                rec.set_face_normal(r, &outward_normal);
+                get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
+                rec.mat_ptr = self.mat_ptr.clone();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        let r = Vec3(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        let center0 = self.center(time0);
+        let center1 = self.center(time1);
+        let box0 = Aabb::new(&center0 - &r, &center0 + &r);
+        let box1 = Aabb::new(&center1 - &r, &center1 + &r);
+        output_box.assign(&surrounding_box(&box0, &box1));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = MovingSphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            Point3::new(0.0, 1.0, -2.0),
+            0.0,
+            1.0,
+            0.5,
+            Arc::new(UninitMaterial {}),
+        );
+        assert!(sphere.center(0.25).y() == 0.25 && sphere.center(1.0).y() == 1.0);
+
+        let mut bbox: Aabb = Default::default();
+        assert!(sphere.bounding_box(0.0, 1.0, &mut bbox));
+        assert!(bbox.min().y() == -0.5 && bbox.max().y() == 1.5);
+        assert!(bbox.min().z() == -2.5 && bbox.max().z() == -1.5);
+
+        // A ray at height 0.9 misses the sphere at time 0 and hits it at time 1.
+        let mut rec: HitRecord = Default::default();
+        let ray = |time| Ray::new(Point3::new(0.0, 0.9, 0.0), Vec3(0.0, 0.0, -1.0), time);
+        assert!(!sphere.hit(&ray(0.0), 0.001, INFINITY, &mut rec));
+        assert!(sphere.hit(&ray(1.0), 0.001, INFINITY, &mut rec));
+        assert!((rec.t - (2.0 - 0.24_f64.sqrt())).abs() < 1e-12);
+    }
+}
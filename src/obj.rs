@@ -0,0 +1,270 @@
+use super::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::sync::Arc;
+
+// Wavefront OBJ loader. Supports v/vt/vn/f statements, triangulates
+// polygonal faces as fans, and maps `usemtl` names onto the given
+// materials. Faces before any `usemtl`, or naming a material that is not
+// in the map, use the default material.
+pub fn load_obj(
+    filename: &str,
+    materials: &HashMap<String, Arc<dyn Material>>,
+    default_material: Arc<dyn Material>,
+) -> std::io::Result<Arc<dyn Hitable>> {
+    let result = std::fs::File::open(filename)
+        .and_then(|file| parse_obj(std::io::BufReader::new(file), materials, default_material));
+    match result {
+        Ok(mesh) => Ok(Arc::new(TriangleMesh::new(mesh))),
+        Err(e) => Err(std::io::Error::new(
+            e.kind(),
+            format!("{}: {}", filename, e),
+        )),
+    }
+}
+
+pub fn parse_obj<R: BufRead>(
+    reader: R,
+    materials: &HashMap<String, Arc<dyn Material>>,
+    default_material: Arc<dyn Material>,
+) -> std::io::Result<MeshData> {
+    let mut mesh = MeshData {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        texcoords: Vec::new(),
+        faces: Vec::new(),
+        materials: vec![default_material],
+    };
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut current_material = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let error = |msg: String| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number, msg),
+            )
+        };
+
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => &line[..],
+        };
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" | "vn" => {
+                if args.len() < 3 {
+                    return Err(error(format!("'{}' needs three coordinates", keyword)));
+                }
+                let mut xyz = [0.0; 3];
+                for (c, arg) in xyz.iter_mut().zip(args.iter()) {
+                    *c = parse_float(arg).map_err(&error)?;
+                }
+                let v = Vec3::new(xyz[0], xyz[1], xyz[2]);
+                if keyword == "v" {
+                    mesh.positions.push(v);
+                } else {
+                    mesh.normals.push(v);
+                }
+            }
+            "vt" => {
+                if args.is_empty() {
+                    return Err(error("'vt' needs at least one coordinate".to_string()));
+                }
+                let u = parse_float(args[0]).map_err(&error)?;
+                let v = match args.get(1) {
+                    Some(arg) => parse_float(arg).map_err(&error)?,
+                    None => 0.0,
+                };
+                mesh.texcoords.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error("face needs at least three vertices".to_string()));
+                }
+                let mut corners = Vec::with_capacity(args.len());
+                for arg in args.iter() {
+                    corners.push(parse_face_vertex(arg, &mesh).map_err(&error)?);
+                }
+
+                // Fan triangulation around the first corner.
+                for i in 1..corners.len() - 1 {
+                    let (a, b, c) = (&corners[0], &corners[i], &corners[i + 1]);
+                    mesh.faces.push(MeshFace {
+                        vertices: [a.0, b.0, c.0],
+                        texcoords: match (a.1, b.1, c.1) {
+                            (Some(ta), Some(tb), Some(tc)) => Some([ta, tb, tc]),
+                            _ => None,
+                        },
+                        normals: match (a.2, b.2, c.2) {
+                            (Some(na), Some(nb), Some(nc)) => Some([na, nb, nc]),
+                            _ => None,
+                        },
+                        material: current_material,
+                    });
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                current_material = match material_indices.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let index = match materials.get(&name) {
+                            Some(material) => {
+                                mesh.materials.push(material.clone());
+                                mesh.materials.len() - 1
+                            }
+                            None => 0,
+                        };
+                        material_indices.insert(name, index);
+                        index
+                    }
+                };
+            }
+            // Groups, objects, smoothing groups and material libraries are ignored.
+            _ => {}
+        }
+    }
+
+    if mesh.faces.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "no faces found",
+        ));
+    }
+    Ok(mesh)
+}
+
+fn parse_float(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .map_err(|_| format!("invalid number '{}'", s))
+}
+
+// Resolves a 1-based (or negative, relative) OBJ index into a 0-based one.
+fn resolve_index(s: &str, count: usize) -> Result<usize, String> {
+    let i: i64 = s.parse().map_err(|_| format!("invalid index '{}'", s))?;
+    let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", i));
+    }
+    Ok(resolved as usize)
+}
+
+// Parses one face corner of the form v, v/vt, v//vn or v/vt/vn.
+fn parse_face_vertex(
+    s: &str,
+    mesh: &MeshData,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = s.split('/');
+    let v = resolve_index(parts.next().unwrap_or(""), mesh.positions.len())?;
+    let vt = match parts.next() {
+        Some(p) if !p.is_empty() => Some(resolve_index(p, mesh.texcoords.len())?),
+        _ => None,
+    };
+    let vn = match parts.next() {
+        Some(p) if !p.is_empty() => Some(resolve_index(p, mesh.normals.len())?),
+        _ => None,
+    };
+    Ok((v, vt, vn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj() {
+        let source = "\
+# unit square in the xy plane
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 -1/4/1
+";
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let mut materials = HashMap::new();
+        materials.insert("red".to_string(), red);
+        let mesh = parse_obj(source.as_bytes(), &materials, Arc::new(UninitMaterial {})).unwrap();
+
+        assert!(mesh.faces.len() == 2);
+        assert!(mesh.faces[1].vertices == [0, 2, 3]);
+        assert!(mesh.faces[0].material == 1);
+
+        let mesh = TriangleMesh::new(mesh);
+
+        let mut rec: HitRecord = Default::default();
+        let r = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(mesh.hit(&r, 0.001, INFINITY, &mut rec));
+        assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.75).abs() < 1e-12);
+        assert!(rec.normal.z() == 1.0);
+    }
+
+    #[test]
+    fn test_parse_obj_normals() {
+        // The vertex normals lean apart, so the shading normal turns across
+        // the face.
+        let source = "\
+v -1 0 0
+v 1 0 0
+v 0 1 0
+vn -1 0 1
+vn 1 0 1
+vn 0 0 1
+f 1//1 2//2 3//3
+";
+        let mesh = parse_obj(
+            source.as_bytes(),
+            &HashMap::new(),
+            Arc::new(UninitMaterial {}),
+        );
+        let mesh = TriangleMesh::new(mesh.unwrap());
+        let mut rec: HitRecord = Default::default();
+        let r = Ray::new(Point3::new(-0.5, 0.25, 1.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(mesh.hit(&r, 0.001, INFINITY, &mut rec));
+        assert!(rec.normal.x() < -0.3 && rec.normal.z() > 0.0);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_obj_errors() {
+        let materials = HashMap::new();
+        let e = parse_obj(
+            "v 0 0 0\nv 1 0\n".as_bytes(),
+            &materials,
+            Arc::new(UninitMaterial {}),
+        )
+        .err()
+        .unwrap();
+        assert!(e.to_string() == "line 2: 'v' needs three coordinates");
+        let e = parse_obj(
+            "v 0 0 0\nf 1 2 3\n".as_bytes(),
+            &materials,
+            Arc::new(UninitMaterial {}),
+        )
+        .err()
+        .unwrap();
+        assert!(e.to_string() == "line 2: index 2 out of range");
+
+        let e = load_obj("no/such/mesh.obj", &materials, Arc::new(UninitMaterial {}));
+        assert!(e
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("no/such/mesh.obj: "));
+    }
+}
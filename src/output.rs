@@ -0,0 +1,145 @@
+use super::*;
+use std::io::Write;
+
+// Writes the framebuffer to filename, choosing the format by extension.
+// .png and binary (P6) .ppm are tone mapped 8-bit sRGB; .pfm, Radiance .hdr
+// and OpenEXR .exr keep the unclamped linear radiance. The image is written
+// beside filename and renamed over it, so a render stopped in the middle of
+// a write still leaves the previous image intact.
+pub fn write_image(
+    filename: &str,
+    fb: &Framebuffer,
+    tone_mapper: &ToneMapper,
+) -> std::io::Result<()> {
+    let extension = image_extension(filename);
+    let partial = partial_path(filename);
+    let result = match extension.as_deref() {
+        Some("png") => write_png(&partial, fb, tone_mapper),
+        Some("ppm") => std::fs::File::create(&*partial)
+            .and_then(|file| write_ppm(&mut std::io::BufWriter::new(file), fb, tone_mapper)),
+        Some("pfm") => std::fs::File::create(&*partial)
+            .and_then(|file| write_pfm(&mut std::io::BufWriter::new(file), &fb.to_hdr_image())),
+        Some("hdr") => std::fs::File::create(&*partial).and_then(|file| {
+            write_radiance_hdr(&mut std::io::BufWriter::new(file), &fb.to_hdr_image())
+        }),
+        Some("exr") => write_exr(&partial, &fb.to_hdr_image()),
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{}: unsupported image format; use .png, .ppm, .pfm, .hdr or .exr",
+                    filename
+                ),
+            ))
+        }
+    };
+    result
+        .and_then(|_| std::fs::rename(&*partial, filename))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&*partial);
+            std::io::Error::new(e.kind(), format!("{}: {}", filename, e))
+        })
+}
+
+// The hidden file beside filename that write_image writes first.
+fn partial_path(filename: &str) -> String {
+    let path = std::path::Path::new(filename);
+    match path.file_name() {
+        Some(name) => path
+            .with_file_name(format!(".{}", name.to_string_lossy()))
+            .to_string_lossy()
+            .into_owned(),
+        None => filename.to_string(),
+    }
+}
+
+// Checks that filename's directory takes new files, by creating and removing
+// the file write_image would write first, so that a bad path is reported
+// before a render rather than after it.
+pub fn check_writable(filename: &str) -> std::io::Result<()> {
+    let partial = partial_path(filename);
+    std::fs::File::create(&partial)
+        .and_then(|_| std::fs::remove_file(&partial))
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))
+}
+
+// The lowercase extension of filename.
+pub fn image_extension(filename: &str) -> Option<String> {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+// Whether write_image knows the format of filename.
+pub fn is_image_format(filename: &str) -> bool {
+    matches!(
+        image_extension(filename).as_deref(),
+        Some("png" | "ppm" | "pfm" | "hdr" | "exr")
+    )
+}
+
+// Tone mapped 8-bit sRGB pixels, top row first.
+pub fn srgb_bytes(fb: &Framebuffer, tone_mapper: &ToneMapper) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(fb.width * fb.height * 3);
+    for j in 0..fb.height {
+        for i in 0..fb.width {
+            bytes.extend_from_slice(&to_srgb8(&tone_mapper.map(&fb.pixel(i, j))));
+        }
+    }
+    bytes
+}
+
+pub fn write_png(
+    filename: &str,
+    fb: &Framebuffer,
+    tone_mapper: &ToneMapper,
+) -> std::io::Result<()> {
+    image::save_buffer(
+        filename,
+        &srgb_bytes(fb, tone_mapper),
+        fb.width as u32,
+        fb.height as u32,
+        image::ColorType::Rgb8,
+    )
+    .map_err(std::io::Error::other)
+}
+
+// Scanline OpenEXR with 32-bit float RGB channels.
+pub fn write_exr(filename: &str, img: &HdrImage) -> std::io::Result<()> {
+    let mut data = Vec::with_capacity(img.width * img.height * 3);
+    for c in img.data.iter() {
+        data.extend_from_slice(&[c.x() as f32, c.y() as f32, c.z() as f32]);
+    }
+    let buffer = image::Rgb32FImage::from_raw(img.width as u32, img.height as u32, data)
+        .expect("image data matches its dimensions");
+    buffer.save(filename).map_err(std::io::Error::other)
+}
+
+pub fn write_ppm<W: Write>(
+    out: &mut W,
+    fb: &Framebuffer,
+    tone_mapper: &ToneMapper,
+) -> std::io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", fb.width, fb.height)?;
+    out.write_all(&srgb_bytes(fb, tone_mapper))?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ppm() {
+        let mut fb = Framebuffer::new(2, 1);
+        fb.sums = vec![Color::new(2.0, 0.0, 0.0), Color::new(0.0, 0.5, 4.0)];
+        fb.samples = vec![2, 1];
+        let mut bytes = Vec::new();
+        let tone_mapper: ToneMapper = Default::default();
+        write_ppm(&mut bytes, &fb, &tone_mapper).unwrap();
+        assert!(bytes == b"P6\n2 1\n255\n\xff\x00\x00\x00\xbc\xff".to_vec());
+        assert!(write_image("image.tga", &fb, &tone_mapper).is_err());
+        assert!(check_writable("no/such/dir/image.png").is_err());
+    }
+}
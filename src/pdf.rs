@@ -0,0 +1,137 @@
+use super::*;
+
+// A probability density over directions on the unit sphere, paired with a
+// way to draw directions from it.
+pub trait Pdf: Send + Sync {
+    fn value(&self, direction: &Vec3) -> f64;
+    fn generate(&self) -> Vec3;
+}
+
+pub struct CosinePdf {
+    pub uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3) -> Self {
+        Self {
+            uvw: Onb::build_from_w(w),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = dot(&unit_vector(direction), self.uvw.w());
+        if cosine <= 0.0 {
+            0.0
+        } else {
+            cosine / PI
+        }
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.local_v(&random_cosine_direction())
+    }
+}
+
+// Directions toward a sampleable hitable, typically the scene's light list.
+pub struct HitablePdf<'a> {
+    pub objects: &'a dyn Hitable,
+    pub origin: Point3,
+}
+
+impl<'a> HitablePdf<'a> {
+    pub fn new(objects: &'a dyn Hitable, origin: &Point3) -> Self {
+        Self {
+            objects,
+            origin: origin.clone(),
+        }
+    }
+}
+
+impl<'a> Pdf for HitablePdf<'a> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.objects.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.objects.random(&self.origin)
+    }
+}
+
+// The light sampling strategy for next-event estimation: the scene's light
+// list and, when it can be importance sampled, the background, each chosen
+// with equal probability.
+pub struct LightPdf<'a> {
+    pub lights: HitablePdf<'a>,
+    pub background: &'a dyn Background,
+    pub lights_weight: f64,
+    pub background_weight: f64,
+}
+
+impl<'a> LightPdf<'a> {
+    pub fn new(lights: &'a HitableList, background: &'a dyn Background, origin: &Point3) -> Self {
+        let has_lights = !lights.objects.is_empty();
+        let has_background = background.is_sampleable();
+        let (lights_weight, background_weight) = match (has_lights, has_background) {
+            (true, true) => (0.5, 0.5),
+            (true, false) => (1.0, 0.0),
+            (false, true) => (0.0, 1.0),
+            (false, false) => (0.0, 0.0),
+        };
+        Self {
+            lights: HitablePdf::new(lights, origin),
+            background,
+            lights_weight,
+            background_weight,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights_weight == 0.0 && self.background_weight == 0.0
+    }
+}
+
+impl<'a> Pdf for LightPdf<'a> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let mut value = 0.0;
+        if self.lights_weight > 0.0 {
+            value += self.lights_weight * self.lights.value(direction);
+        }
+        if self.background_weight > 0.0 {
+            value += self.background_weight * self.background.pdf_value(direction);
+        }
+        value
+    }
+
+    fn generate(&self) -> Vec3 {
+        if sample_1d() < self.lights_weight {
+            self.lights.generate()
+        } else {
+            self.background.random()
+        }
+    }
+}
+
+// Multiple importance sampling weight (Veach's power heuristic, beta = 2)
+// for a sample drawn from f when g could also have produced it.
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f2 = f_pdf * f_pdf;
+    let g2 = g_pdf * g_pdf;
+    if f2 + g2 == 0.0 {
+        return 0.0;
+    }
+    f2 / (f2 + g2)
+}
+
+pub struct SpherePdf {}
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self) -> Vec3 {
+        random_unit_vector()
+    }
+}
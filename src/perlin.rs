@@ -0,0 +1,151 @@
+use super::*;
+use rand::SeedableRng;
+
+pub struct Perlin {
+    pub ranvec: Vec<Vec3>,
+    pub perm_x: Vec<usize>,
+    pub perm_y: Vec<usize>,
+    pub perm_z: Vec<usize>,
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Perlin {
+    const POINT_COUNT: usize = 256;
+
+    // Draws from the thread's generator, so --seed reproduces the noise.
+    pub fn new() -> Self {
+        with_random(Self::from_rng)
+    }
+
+    // The same seed always produces the same noise field.
+    pub fn with_seed(seed: u32) -> Self {
+        // XorShift must not be seeded with all zeros.
+        let mut rng = rand::XorShiftRng::from_seed([seed, 0x9e37_79b9, 0x7f4a_7c15, 0x2545_f491]);
+        Self::from_rng(&mut rng)
+    }
+
+    fn from_rng<R: Rng>(rng: &mut R) -> Self {
+        let ranvec = (0..Self::POINT_COUNT)
+            .map(|_| {
+                let v = Vec3::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                );
+                unit_vector(&v)
+            })
+            .collect();
+
+        Self {
+            ranvec,
+            perm_x: Self::perlin_generate_perm(rng),
+            perm_y: Self::perlin_generate_perm(rng),
+            perm_z: Self::perlin_generate_perm(rng),
+        }
+    }
+
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mut c: [[[Vec3; 2]; 2]; 2] = Default::default();
+        for (di, ci) in c.iter_mut().enumerate() {
+            for (dj, cj) in ci.iter_mut().enumerate() {
+                for (dk, ck) in cj.iter_mut().enumerate() {
+                    *ck = self.ranvec[self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize]]
+                        .clone();
+                }
+            }
+        }
+
+        Self::perlin_interp(&c, u, v, w)
+    }
+
+    pub fn turb(&self, p: &Point3, depth: i64) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p.clone();
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+
+    fn perlin_generate_perm<R: Rng>(rng: &mut R) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..Self::POINT_COUNT).collect();
+        Self::permute(rng, &mut p);
+        p
+    }
+
+    fn permute<R: Rng>(rng: &mut R, p: &mut [usize]) {
+        for i in (1..p.len()).rev() {
+            let target = rng.gen_range(0, i + 1);
+            p.swap(i, target);
+        }
+    }
+
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        // Hermite cubic smoothing of the interpolation weights.
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+        let mut accum = 0.0;
+
+        for (i, ci) in c.iter().enumerate() {
+            for (j, cj) in ci.iter().enumerate() {
+                for (k, ck) in cj.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * dot(ck, &weight_v);
+                }
+            }
+        }
+
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_seed() {
+        let a = Perlin::with_seed(42);
+        let b = Perlin::with_seed(42);
+        let p = Point3::new(1.3, -2.7, 0.45);
+        assert!(a.perm_x == b.perm_x);
+        assert!(a.noise(&p) == b.noise(&p));
+        assert!(a.turb(&p, 7) == b.turb(&p, 7));
+    }
+
+    #[test]
+    fn test_noise_range() {
+        let perlin = Perlin::with_seed(7);
+        for i in 0..100 {
+            let t = i as f64 * 0.37;
+            let n = perlin.noise(&Point3::new(t, 0.5 * t, -t));
+            assert!((-1.0..=1.0).contains(&n));
+        }
+        // Noise vanishes on the integer lattice.
+        assert!(perlin.noise(&Point3::new(3.0, -1.0, 2.0)) == 0.0);
+    }
+}
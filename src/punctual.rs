@@ -0,0 +1,167 @@
+use super::*;
+
+// Where a punctual light's illumination comes from, as seen from a point.
+pub struct LightSample {
+    // Unit direction from the shaded point towards the light.
+    pub direction: Vec3,
+    // Distance to the light along direction; INFINITY for directional lights.
+    pub distance: f64,
+    // Irradiance arriving perpendicular to direction.
+    pub irradiance: Color,
+}
+
+impl Default for LightSample {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            distance: INFINITY,
+            irradiance: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+// Lights with no area or no extent in direction. Rays can never hit them, so
+// the integrator samples every one with a shadow ray at each diffuse hit.
+pub trait PunctualLight: Send + Sync {
+    // Returns false when the light does not reach p at all.
+    fn sample(&self, p: &Point3, sample: &mut LightSample) -> bool;
+}
+
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl PunctualLight for PointLight {
+    fn sample(&self, p: &Point3, sample: &mut LightSample) -> bool {
+        let to_light = &self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return false;
+        }
+        sample.distance = distance_squared.sqrt();
+        sample.direction = &to_light / sample.distance;
+        sample.irradiance = &self.intensity / distance_squared;
+        true
+    }
+}
+
+// A point light restricted to a cone. Angles are half-angles in degrees:
+// full intensity inside falloff_start, fading smoothly to nothing at
+// total_width.
+pub struct SpotLight {
+    pub position: Point3,
+    pub direction: Vec3,
+    pub intensity: Color,
+    pub cos_total_width: f64,
+    pub cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Color,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> Self {
+        let direction = unit_vector(&(&target - &position));
+        Self {
+            position,
+            direction,
+            intensity,
+            cos_total_width: degrees_to_radians(total_width).cos(),
+            cos_falloff_start: degrees_to_radians(falloff_start.min(total_width)).cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta <= self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let t =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl PunctualLight for SpotLight {
+    fn sample(&self, p: &Point3, sample: &mut LightSample) -> bool {
+        let to_light = &self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return false;
+        }
+        sample.distance = distance_squared.sqrt();
+        sample.direction = &to_light / sample.distance;
+        let falloff = self.falloff(-dot(&sample.direction, &self.direction));
+        if falloff == 0.0 {
+            return false;
+        }
+        sample.irradiance = (falloff / distance_squared) * &self.intensity;
+        true
+    }
+}
+
+// Parallel light from infinitely far away, such as the sun. direction is the
+// way the light travels.
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction: unit_vector(&direction),
+            irradiance,
+        }
+    }
+}
+
+impl PunctualLight for DirectionalLight {
+    fn sample(&self, _p: &Point3, sample: &mut LightSample) -> bool {
+        sample.direction = -&self.direction;
+        sample.distance = INFINITY;
+        sample.irradiance = self.irradiance.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spot_light() {
+        let light = SpotLight::new(
+            Point3::new(0.0, 2.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Color::new(4.0, 4.0, 4.0),
+            30.0,
+            20.0,
+        );
+        let mut sample: LightSample = Default::default();
+        assert!(light.sample(&Point3::new(0.0, 0.0, 0.0), &mut sample));
+        assert!(sample.distance == 2.0 && sample.direction.y() == 1.0);
+        assert!(sample.irradiance.x() == 1.0);
+
+        // Half way through the falloff band, and outside the cone.
+        let x = 2.0 * degrees_to_radians(25.0).tan();
+        assert!(light.sample(&Point3::new(x, 0.0, 0.0), &mut sample));
+        assert!(sample.irradiance.x() > 0.0 && sample.irradiance.x() < 1.0);
+        assert!(!light.sample(&Point3::new(2.0, 0.0, 0.0), &mut sample));
+    }
+}
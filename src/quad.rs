@@ -0,0 +1,136 @@
+use super::*;
+use std::sync::Arc;
+
+// A parallelogram spanned by the edge vectors u and v from the corner q.
+// The outward normal is cross(u, v), and (u, v) texture coordinates run
+// from 0 to 1 along each edge.
+pub struct Quad {
+    pub q: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub mat_ptr: Arc<dyn Material>,
+    pub bbox: Aabb,
+    pub normal: Vec3,
+    pub d: f64,
+    pub w: Vec3,
+    pub area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat_ptr: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        let normal = unit_vector(&n);
+        let d = dot(&normal, &q);
+        let w = &n / dot(&n, &n);
+        let area = n.length();
+
+        // Pad the box so that axis-aligned quads never have zero thickness.
+        let delta = Vec3(0.0001, 0.0001, 0.0001);
+        let corner = &(&q + &u) + &v;
+        let bbox = surrounding_box(
+            &Aabb::new(q.clone(), corner.clone()),
+            &Aabb::new(&q + &u, &q + &v),
+        );
+        let bbox = Aabb::new(bbox.min() - &delta, bbox.max() + &delta);
+
+        Self {
+            q,
+            u,
+            v,
+            mat_ptr,
+            bbox,
+            normal,
+            d,
+            w,
+            area,
+        }
+    }
+}
+
+impl Hitable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let denom = dot(&self.normal, r.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // Return false if the hit point parameter t is outside the ray interval.
+        let t = (self.d - dot(&self.normal, r.origin())) / denom;
+        if t <= t_min || t >= t_max {
+            return false;
+        }
+
+        // Determine the hit point lies within the planar shape using its plane coordinates.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = &intersection - &self.q;
+        let alpha = dot(&self.w, &cross(&planar_hitpt_vector, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar_hitpt_vector));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.u = alpha;
+        rec.v = beta;
+        rec.t = t;
+        rec.p = intersection;
+        rec.mat_ptr = self.mat_ptr.clone();
+        rec.set_face_normal(r, &self.normal);
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        output_box.assign(&self.bbox);
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let mut rec: HitRecord = Default::default();
+        if !self.hit(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            0.001,
+            INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let (s, t) = sample_2d();
+        let p = &(&self.q + &(s * &self.u)) + &(t * &self.v);
+        &p - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let quad = Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3(2.0, 0.0, 0.0),
+            Vec3(0.0, 4.0, 0.0),
+            Arc::new(UninitMaterial {}),
+        );
+        let mut rec: HitRecord = Default::default();
+        let r = Ray::new(Point3::new(0.5, 1.0, 5.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(quad.hit(&r, 0.001, INFINITY, &mut rec));
+        assert!(rec.t == 5.0);
+        assert!(rec.u == 0.25 && rec.v == 0.25);
+        assert!(rec.front_face && rec.normal.z() == 1.0);
+
+        let r = Ray::new(Point3::new(2.5, 1.0, 5.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(!quad.hit(&r, 0.001, INFINITY, &mut rec));
+        let r = Ray::new(Point3::new(0.5, 1.0, 5.0), Vec3(1.0, 0.0, 0.0), 0.0);
+        assert!(!quad.hit(&r, 0.001, INFINITY, &mut rec));
+    }
+}
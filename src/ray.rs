@@ -1,22 +1,13 @@
 use super::Point3;
 use super::Vec3;
 
+#[derive(Default)]
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
     pub tm: f64,
 }
 
-impl Default for Ray {
-    fn default() -> Self {
-        Self {
-            orig: Default::default(),
-            dir: Default::default(),
-            tm: Default::default(),
-        }
-    }
-}
-
 impl Ray {
     pub fn new(orig: Point3, dir: Vec3, tm: f64) -> Self {
         Ray { orig, dir, tm }
@@ -37,12 +28,6 @@ impl Ray {
     pub fn at(&self, t: f64) -> Vec3 {
         &self.orig + &(t * &self.dir)
     }
-
-    pub fn assign(&mut self, r: &Ray) {
-        self.orig = r.orig.clone();
-        self.dir = r.dir.clone();
-        self.tm = r.tm;
-    }
 }
 
 // #[cfg(test)]
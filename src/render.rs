@@ -0,0 +1,260 @@
+use super::*;
+use std::io::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::mpsc;
+
+// The scene and the render settings the worker threads share.
+pub struct Renderer<'a> {
+    pub world: &'a dyn Hitable,
+    pub camera: &'a Camera,
+    pub background: &'a dyn Background,
+    pub lights: &'a HitableList,
+    pub punctual_lights: &'a [Arc<dyn PunctualLight>],
+    pub sampler: Arc<dyn Sampler>,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub seed: u64,
+    pub max_depth: i64,
+    pub thread_count: usize,
+}
+
+impl Renderer<'_> {
+    // Takes samples first_sample..end_sample of every pixel, adding them to
+    // the framebuffer and to stats, which is indexed like the framebuffer.
+    // Pixels that adaptive sampling finds converged take no more. Each
+    // sample depends only on the seed, its pixel and its index, and is added
+    // to the pixel's running sum in index order, so an image comes out bit
+    // for bit the same however its samples are split into passes.
+    pub fn render_pass<W: Write>(
+        &self,
+        framebuffer: &mut Framebuffer,
+        stats: &mut [PixelStats],
+        first_sample: i64,
+        end_sample: i64,
+        err: &mut W,
+    ) -> std::io::Result<()> {
+        let (image_width, image_height) = (framebuffer.width as i64, framebuffer.height as i64);
+
+        // Worker threads pull scanlines top to bottom from a shared counter
+        // and send finished rows back; the main thread reassembles them.
+        let next_scanline = AtomicI64::new(image_height - 1);
+        let (tx, rx) = mpsc::channel();
+        let mut rows = Vec::with_capacity(image_height as usize);
+        std::thread::scope(|scope| -> std::io::Result<()> {
+            for _ in 0..self.thread_count {
+                let tx = tx.clone();
+                let (next_scanline, framebuffer, stats) = (&next_scanline, &*framebuffer, &*stats);
+                let sampler = self.sampler.clone();
+                scope.spawn(move || {
+                    set_sampler(Some(sampler));
+                    loop {
+                        let j = next_scanline.fetch_sub(1, Ordering::Relaxed);
+                        if j < 0 {
+                            break;
+                        }
+                        let row_index = (image_height - 1 - j) as usize;
+                        let mut row = Vec::with_capacity(image_width as usize);
+                        for i in 0..image_width {
+                            let index = row_index * image_width as usize + i as usize;
+                            let mut pixel_color = framebuffer.sums[index].clone();
+                            let mut pixel_stats = stats[index].clone();
+                            self.render_pixel(
+                                i,
+                                j,
+                                image_width,
+                                image_height,
+                                first_sample..end_sample,
+                                &mut pixel_color,
+                                &mut pixel_stats,
+                            );
+                            row.push((pixel_color, pixel_stats));
+                        }
+                        if tx.send((row_index, row)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            for remaining in (0..image_height).rev() {
+                writeln!(err, "Scanlines remaining: {} ", remaining)?;
+                err.flush()?;
+                rows.push(rx.recv().expect("render thread exited early"));
+            }
+            Ok(())
+        })?;
+
+        for (row_index, row) in rows {
+            for (i, (pixel_color, pixel_stats)) in row.into_iter().enumerate() {
+                let index = row_index * framebuffer.width + i;
+                framebuffer.sums[index] = pixel_color;
+                framebuffer.samples[index] = pixel_stats.count;
+                stats[index] = pixel_stats;
+            }
+        }
+        Ok(())
+    }
+
+    // Adds the given samples of pixel (i, j), counted from the bottom left,
+    // to its running sum and stats, which count only the samples kept.
+    #[allow(clippy::too_many_arguments)]
+    fn render_pixel(
+        &self,
+        i: i64,
+        j: i64,
+        image_width: i64,
+        image_height: i64,
+        samples: std::ops::Range<i64>,
+        pixel_color: &mut Color,
+        stats: &mut PixelStats,
+    ) {
+        let pixel = (j * image_width + i) as u64;
+        for s in samples {
+            if self.adaptive.is_some_and(|a| a.is_converged(stats)) {
+                break;
+            }
+            seed_sample(self.seed, pixel, s as u64);
+            start_sample(i, j, s as u64);
+            let (du, dv) = sample_2d();
+            let u = (i as f64 + du) / (image_width as f64 - 1.0);
+            let v = (j as f64 + dv) / (image_height as f64 - 1.0);
+            let r = self.camera.get_ray(u, v);
+            let sample_color = ray_color(
+                &r,
+                self.background,
+                self.world,
+                self.lights,
+                self.punctual_lights,
+                self.max_depth,
+            );
+            // A NaN or infinite sample would spoil the pixel for good, so it
+            // is dropped from both the sum and the count.
+            if !(sample_color.x().is_finite()
+                && sample_color.y().is_finite()
+                && sample_color.z().is_finite())
+            {
+                continue;
+            }
+            stats.add(luminance(&sample_color));
+            *pixel_color += sample_color;
+        }
+    }
+}
+
+// The sample ranges of progressive passes: the first takes one sample per
+// pixel and each after that doubles the total, so every snapshot holds a
+// power-of-two count, the counts the low-discrepancy samplers stratify best.
+pub fn progressive_passes(samples_per_pixel: i64) -> Vec<(i64, i64)> {
+    let mut passes = Vec::new();
+    let mut first = 0;
+    let mut end = 1;
+    loop {
+        passes.push((first, end.min(samples_per_pixel)));
+        if end >= samples_per_pixel {
+            return passes;
+        }
+        first = end;
+        end *= 2;
+    }
+}
+
+// Splits passes so that none takes more than max_samples per pixel.
+pub fn split_passes(passes: &[(i64, i64)], max_samples: i64) -> Vec<(i64, i64)> {
+    let mut split = Vec::new();
+    for &(first, end) in passes {
+        let mut start = first;
+        while start < end {
+            split.push((start, (start + max_samples).min(end)));
+            start += max_samples;
+        }
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progressive_passes() {
+        assert!(progressive_passes(1) == vec![(0, 1)]);
+        assert!(progressive_passes(6) == vec![(0, 1), (1, 2), (2, 4), (4, 6)]);
+        assert!(split_passes(&[(0, 1), (1, 6)], 2) == vec![(0, 1), (1, 3), (3, 5), (5, 6)]);
+    }
+
+    // Builds a noise-textured scene after seeding, as main does, and renders
+    // it, returning the bits of the pixel sums.
+    fn render_noise_scene(seed: u64, thread_count: usize) -> Vec<[u64; 3]> {
+        seed_random(seed);
+        let marble = Arc::new(MarbleTexture::new(4.0, Color::new(0.9, 0.8, 0.7)));
+        let mut world = HitableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 1.0),
+            0.8,
+            Arc::new(Lambertian::from_texture(marble)),
+        )));
+        let camera: Camera = Default::default();
+        let background: GradientBackground = Default::default();
+        let renderer = Renderer {
+            world: &world,
+            camera: &camera,
+            background: &background,
+            lights: &HitableList::new(),
+            punctual_lights: &[],
+            sampler: new_sampler(SamplerType::Sobol, seed, 4),
+            adaptive: None,
+            seed,
+            max_depth: 4,
+            thread_count,
+        };
+        let mut framebuffer = Framebuffer::new(12, 8);
+        let mut stats = vec![PixelStats::default(); 12 * 8];
+        renderer
+            .render_pass(&mut framebuffer, &mut stats, 0, 4, &mut std::io::sink())
+            .unwrap();
+        framebuffer
+            .sums
+            .iter()
+            .map(|c| [c.x().to_bits(), c.y().to_bits(), c.z().to_bits()])
+            .collect()
+    }
+
+    #[test]
+    fn test_render_drops_nan_samples() {
+        let mut world = HitableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 1.0),
+            0.3,
+            Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0))),
+        )));
+        let camera: Camera = Default::default();
+        let background = SolidBackground::new(Color::new(f64::NAN, 0.0, 0.0));
+        let renderer = Renderer {
+            world: &world,
+            camera: &camera,
+            background: &background,
+            lights: &HitableList::new(),
+            punctual_lights: &[],
+            sampler: new_sampler(SamplerType::Sobol, 1, 4),
+            adaptive: None,
+            seed: 1,
+            max_depth: 4,
+            thread_count: 1,
+        };
+        let mut framebuffer = Framebuffer::new(8, 8);
+        let mut stats = vec![PixelStats::default(); 8 * 8];
+        renderer
+            .render_pass(&mut framebuffer, &mut stats, 0, 4, &mut std::io::sink())
+            .unwrap();
+        assert!(framebuffer.samples[0] == 0 && framebuffer.samples[4 * 8 + 4] == 4);
+        assert!(framebuffer.sums[4 * 8 + 4].x() == 4.0);
+        assert!(framebuffer.sums.iter().all(|c| !c.x().is_nan()));
+    }
+
+    #[test]
+    fn test_render_is_independent_of_threads() {
+        let one = render_noise_scene(9, 1);
+        assert!(one == render_noise_scene(9, 3));
+        assert!(one != render_noise_scene(10, 1));
+    }
+}
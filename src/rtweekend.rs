@@ -1,7 +1,15 @@
 use super::*;
+use rand::SeedableRng;
+use std::cell::RefCell;
+
+thread_local! {
+    // Each render thread owns its own generator. It starts from the OS-backed
+    // thread_rng, and is reseeded for every sample when rendering.
+    static RNG: RefCell<rand::XorShiftRng> = RefCell::new(rand::thread_rng().gen());
+}
 // Constants
 pub const INFINITY: f64 = f64::INFINITY;
-pub const PI: f64 = 3.1415926535897932385;
+pub const PI: f64 = std::f64::consts::PI;
 
 // Utility Functions
 pub fn degrees_to_radians(degrees: f64) -> f64 {
@@ -19,15 +27,55 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
 }
 
 pub fn random_double() -> f64 {
-    let mut rng = rand::thread_rng();
-    rng.gen()
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+// Runs f with this thread's generator, for code that needs a whole Rng,
+// so that it follows seed_random like everything else.
+pub fn with_random<T>(f: impl FnOnce(&mut rand::XorShiftRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+// The splitmix64 finalizer; scrambles all 64 bits of z.
+pub fn mix64(z: u64) -> u64 {
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Reseeds this thread's generator so that a run can be repeated.
+pub fn seed_random(seed: u64) {
+    // Spread the seed over the generator's state with splitmix64. The state
+    // must not be all zero.
+    let a = mix64(seed.wrapping_add(0x9e37_79b9_7f4a_7c15));
+    let b = mix64(seed.wrapping_add(0x3c6e_f372_fe94_f82a));
+    let words = [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1];
+    RNG.with(|rng| *rng.borrow_mut() = rand::XorShiftRng::from_seed(words));
+}
+
+// Reseeds this thread's generator for one sample of one pixel. Every random
+// number a sample uses then depends only on the seed and its coordinates,
+// not on which thread traces it or what that thread traced before.
+pub fn seed_sample(seed: u64, pixel: u64, sample: u64) {
+    seed_random(mix64(seed ^ mix64(pixel ^ mix64(sample))));
 }
 
 pub fn random_double_minmax(min: f64, max: f64) -> f64 {
     min + (max - min) * random_double()
 }
 
-pub fn random_int(min: i64, max: i64) -> i64 {
-    // Returns a random integer in [min,max].
-    random_double_minmax(min as f64, (max + 1) as f64) as i64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_sample() {
+        seed_sample(7, 12, 3);
+        let a: Vec<f64> = (0..4).map(|_| random_double()).collect();
+        seed_sample(7, 12, 4);
+        let b: Vec<f64> = (0..4).map(|_| random_double()).collect();
+        seed_sample(7, 12, 3);
+        let c: Vec<f64> = (0..4).map(|_| random_double()).collect();
+        assert!(a == c && a != b);
+    }
 }
@@ -0,0 +1,525 @@
+use super::*;
+use std::cell::RefCell;
+
+// Identifies one sample: the pixel it belongs to and its index there.
+#[derive(Clone, Copy, Debug)]
+pub struct SampleIndex {
+    pub i: i64,
+    pub j: i64,
+    pub index: u64,
+}
+
+// A source of sample values for the integrator. A sample asks for its
+// dimensions in a fixed order (pixel jitter, lens, time, then two or so
+// per bounce), and a sampler spreads each dimension evenly over a pixel's
+// samples instead of leaving it to white noise. Every value the integrator
+// draws, light and medium sampling included, goes through sample_1d or
+// sample_2d; random_double is left to scene construction.
+pub trait Sampler: Send + Sync {
+    // Dimension `dimension` of the sample, in [0, 1).
+    fn get_1d(&self, sample: &SampleIndex, dimension: u32) -> f64;
+    // Dimensions `dimension` and `dimension + 1`, as a pair.
+    fn get_2d(&self, sample: &SampleIndex, dimension: u32) -> (f64, f64);
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SamplerType {
+    Random,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+pub fn new_sampler(
+    sampler_type: SamplerType,
+    seed: u64,
+    samples_per_pixel: u64,
+) -> Arc<dyn Sampler> {
+    match sampler_type {
+        SamplerType::Random => Arc::new(RandomSampler {}),
+        SamplerType::Stratified => Arc::new(StratifiedSampler::new(seed, samples_per_pixel)),
+        SamplerType::Halton => Arc::new(HaltonSampler::new(seed)),
+        SamplerType::Sobol => Arc::new(SobolSampler::new(seed)),
+        SamplerType::BlueNoise => Arc::new(BlueNoiseSampler::new(seed)),
+    }
+}
+
+// The sample being traced on this thread, and how many of its dimensions
+// have been used so far.
+struct SampleCursor {
+    sampler: Arc<dyn Sampler>,
+    sample: SampleIndex,
+    dimension: u32,
+}
+
+thread_local! {
+    static CURSOR: RefCell<Option<SampleCursor>> = const { RefCell::new(None) };
+}
+
+// Makes sampler the source for sample_1d and sample_2d on this thread. With
+// no sampler they return white noise.
+pub fn set_sampler(sampler: Option<Arc<dyn Sampler>>) {
+    CURSOR.with(|cursor| {
+        *cursor.borrow_mut() = sampler.map(|sampler| SampleCursor {
+            sampler,
+            sample: SampleIndex {
+                i: 0,
+                j: 0,
+                index: 0,
+            },
+            dimension: 0,
+        })
+    });
+}
+
+// Starts sample `index` of pixel (i, j), from its first dimension.
+pub fn start_sample(i: i64, j: i64, index: u64) {
+    CURSOR.with(|cursor| {
+        if let Some(cursor) = cursor.borrow_mut().as_mut() {
+            cursor.sample = SampleIndex { i, j, index };
+            cursor.dimension = 0;
+        }
+    });
+}
+
+// The next dimension of the current sample.
+pub fn sample_1d() -> f64 {
+    CURSOR.with(|cursor| match cursor.borrow_mut().as_mut() {
+        Some(cursor) => {
+            let x = cursor.sampler.get_1d(&cursor.sample, cursor.dimension);
+            cursor.dimension += 1;
+            x
+        }
+        None => random_double(),
+    })
+}
+
+// The next two dimensions of the current sample.
+pub fn sample_2d() -> (f64, f64) {
+    CURSOR.with(|cursor| match cursor.borrow_mut().as_mut() {
+        Some(cursor) => {
+            let xy = cursor.sampler.get_2d(&cursor.sample, cursor.dimension);
+            cursor.dimension += 2;
+            xy
+        }
+        None => (random_double(), random_double()),
+    })
+}
+
+// Independent uniform values: the book's original sampling.
+pub struct RandomSampler {}
+
+impl Sampler for RandomSampler {
+    fn get_1d(&self, _sample: &SampleIndex, _dimension: u32) -> f64 {
+        random_double()
+    }
+
+    fn get_2d(&self, _sample: &SampleIndex, _dimension: u32) -> (f64, f64) {
+        (random_double(), random_double())
+    }
+}
+
+// Jittered strata: each of a pixel's samples falls in its own 1/n interval
+// in 1D, or its own cell of a near-square grid in 2D. The strata are
+// shuffled independently per pixel and dimension so that dimensions do not
+// correlate.
+pub struct StratifiedSampler {
+    pub seed: u64,
+    pub samples_per_pixel: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u64) -> Self {
+        Self {
+            seed,
+            samples_per_pixel: samples_per_pixel.max(1),
+        }
+    }
+
+    // Samples past the pixel's count start a fresh, reshuffled set of strata.
+    fn stratum(&self, sample: &SampleIndex, dimension: u32, strata: u64) -> (u64, u64) {
+        let n = self.samples_per_pixel;
+        let h = mix64(pixel_hash(self.seed, sample, dimension) ^ (sample.index / n));
+        let stratum = permute((sample.index % n) as u32, strata as u32, h as u32);
+        (stratum as u64, h ^ (sample.index % n))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get_1d(&self, sample: &SampleIndex, dimension: u32) -> f64 {
+        let n = self.samples_per_pixel;
+        let (stratum, h) = self.stratum(sample, dimension, n);
+        (stratum as f64 + hash_unit(h)) / n as f64
+    }
+
+    fn get_2d(&self, sample: &SampleIndex, dimension: u32) -> (f64, f64) {
+        let n = self.samples_per_pixel;
+        let nx = (n as f64).sqrt().ceil() as u64;
+        let ny = n.div_ceil(nx);
+        let (cell, h) = self.stratum(sample, dimension, nx * ny);
+        (
+            ((cell % nx) as f64 + hash_unit(h)) / nx as f64,
+            ((cell / nx) as f64 + hash_unit(!h)) / ny as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+// The Halton sequence, one prime base per dimension, Owen-scrambled per
+// pixel and dimension. Without the scrambling the larger bases fill only a
+// corner of the unit interval at low sample counts. Dimensions past the
+// table of primes fall back to white noise.
+pub struct HaltonSampler {
+    pub seed: u64,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn get_1d(&self, sample: &SampleIndex, dimension: u32) -> f64 {
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                owen_radical_inverse(base, sample.index, pixel_hash(self.seed, sample, dimension))
+            }
+            None => random_double(),
+        }
+    }
+
+    fn get_2d(&self, sample: &SampleIndex, dimension: u32) -> (f64, f64) {
+        (
+            self.get_1d(sample, dimension),
+            self.get_1d(sample, dimension + 1),
+        )
+    }
+}
+
+// The first two dimensions of the Sobol sequence with Owen scrambling,
+// reused for every pair of dimensions with its own shuffle of the sample
+// order and its own scramble, after Burley's "Practical Hash-based Owen
+// Scrambling". The first 2^k samples of a pixel are stratified in every
+// 1D and 2D projection.
+pub struct SobolSampler {
+    pub seed: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn get_1d(&self, sample: &SampleIndex, dimension: u32) -> f64 {
+        owen_sobol_2d(sample.index, pixel_hash(self.seed, sample, dimension)).0
+    }
+
+    fn get_2d(&self, sample: &SampleIndex, dimension: u32) -> (f64, f64) {
+        owen_sobol_2d(sample.index, pixel_hash(self.seed, sample, dimension))
+    }
+}
+
+pub const BLUE_NOISE_SIZE: usize = 64;
+
+// The same scrambled Sobol points in every pixel, each pixel shifted on
+// the torus by a blue-noise dither value. Neighbouring pixels then get very
+// different shifts, which leaves the remaining error as high-frequency
+// noise that is much less visible than white noise at the same level. The
+// shift costs some per-pixel accuracy against plain Sobol.
+pub struct BlueNoiseSampler {
+    pub seed: u64,
+    pub tile: Vec<f64>,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            tile: blue_noise_tile(BLUE_NOISE_SIZE, seed),
+        }
+    }
+
+    // The tile is offset differently for each dimension so that dimensions
+    // do not share shifts.
+    fn shift(&self, sample: &SampleIndex, h: u64) -> f64 {
+        let size = BLUE_NOISE_SIZE as i64;
+        let i = (sample.i + (h & 0xffff) as i64).rem_euclid(size);
+        let j = (sample.j + ((h >> 16) & 0xffff) as i64).rem_euclid(size);
+        self.tile[(j * size + i) as usize]
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn get_1d(&self, sample: &SampleIndex, dimension: u32) -> f64 {
+        self.get_2d(sample, dimension).0
+    }
+
+    fn get_2d(&self, sample: &SampleIndex, dimension: u32) -> (f64, f64) {
+        let h = mix64(self.seed ^ mix64(dimension as u64 + 1));
+        let (x, y) = owen_sobol_2d(sample.index, h);
+        (
+            wrap(x + self.shift(sample, h)),
+            wrap(y + self.shift(sample, mix64(h))),
+        )
+    }
+}
+
+// Ranks the cells of a size x size torus with Ulichney's void-and-cluster
+// method, giving a dither array whose every threshold is a blue-noise
+// pattern. Values are (rank + 0.5) / size^2.
+pub fn blue_noise_tile(size: usize, seed: u64) -> Vec<f64> {
+    let n = size * size;
+
+    // Energy that a point adds at each toroidal offset from it.
+    let sigma = 1.5;
+    let mut kernel = vec![0.0; n];
+    for dy in 0..size {
+        for dx in 0..size {
+            let x = dx.min(size - dx) as f64;
+            let y = dy.min(size - dy) as f64;
+            kernel[dy * size + dx] = (-(x * x + y * y) / (2.0 * sigma * sigma)).exp();
+        }
+    }
+    let update = |energy: &mut [f64], cell: usize, sign: f64| {
+        let (cx, cy) = (cell % size, cell / size);
+        for y in 0..size {
+            for x in 0..size {
+                let dx = (x + size - cx) % size;
+                let dy = (y + size - cy) % size;
+                energy[y * size + x] += sign * kernel[dy * size + dx];
+            }
+        }
+    };
+    // The cell with the most (or, negated, least) energy among cells in the
+    // given state.
+    let extreme = |pattern: &[bool], energy: &[f64], state: bool, sign: f64| {
+        (0..n)
+            .filter(|&c| pattern[c] == state)
+            .max_by(|&a, &b| (sign * energy[a]).total_cmp(&(sign * energy[b])))
+            .unwrap()
+    };
+
+    // Scatter a tenth of the cells at random, then move the point in the
+    // tightest cluster to the largest void until that changes nothing.
+    let initial = (n / 10).max(1);
+    let mut pattern = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut placed = 0;
+    let mut k = 0;
+    while placed < initial {
+        k += 1;
+        let cell = (mix64(seed ^ mix64(k)) % n as u64) as usize;
+        if !pattern[cell] {
+            pattern[cell] = true;
+            update(&mut energy, cell, 1.0);
+            placed += 1;
+        }
+    }
+    for _ in 0..n {
+        let cluster = extreme(&pattern, &energy, true, 1.0);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = extreme(&pattern, &energy, false, -1.0);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    // Phase 1: rank the initial points by removing tightest clusters.
+    let (mut removed, mut removed_energy) = (pattern.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = extreme(&removed, &removed_energy, true, 1.0);
+        removed[cluster] = false;
+        update(&mut removed_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    // Phase 2: fill the largest voids up to half the cells.
+    for r in initial..n / 2 {
+        let void = extreme(&pattern, &energy, false, -1.0);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+    // Phase 3: the empty cells are now the minority, so fill the tightest
+    // cluster of empty cells instead.
+    let mut energy = vec![0.0; n];
+    for cell in (0..n).filter(|&c| !pattern[c]) {
+        update(&mut energy, cell, 1.0);
+    }
+    for r in (n / 2).max(initial)..n {
+        let cluster = extreme(&pattern, &energy, false, 1.0);
+        pattern[cluster] = true;
+        update(&mut energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    rank.iter().map(|&r| (r as f64 + 0.5) / n as f64).collect()
+}
+
+// Hash of the seed, a pixel and a dimension.
+fn pixel_hash(seed: u64, sample: &SampleIndex, dimension: u32) -> u64 {
+    let pixel = ((sample.i as u64) << 32) ^ (sample.j as u64 & 0xffff_ffff);
+    mix64(seed ^ mix64(pixel ^ mix64(dimension as u64 + 1)))
+}
+
+// A uniform value in [0, 1) from a hash.
+fn hash_unit(h: u64) -> f64 {
+    (mix64(h) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Adds mod 1, for values in [0, 1).
+fn wrap(x: f64) -> f64 {
+    if x >= 1.0 {
+        x - 1.0
+    } else {
+        x
+    }
+}
+
+// Kensler's hashed permutation of [0, l), from "Correlated Multi-Jittered
+// Sampling".
+fn permute(i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    let mut i = i;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            return (i.wrapping_add(p)) % l;
+        }
+    }
+}
+
+// The radical inverse of index in the given base, each digit permuted by a
+// hash of the digits before it. Digits are generated past the last nonzero
+// one, until they fall below double precision, as the scrambled zeros
+// still matter.
+fn owen_radical_inverse(base: u64, index: u64, hash: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut index = index;
+    let mut reversed: u64 = 0;
+    let mut inv_base_n = 1.0;
+    while 1.0 - inv_base_n < 1.0 {
+        let next = index / base;
+        let digit = index - next * base;
+        let digit_hash = mix64(hash ^ reversed) as u32;
+        reversed = reversed * base + permute(digit as u32, base as u32, digit_hash) as u64;
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    (reversed as f64 * inv_base_n).min(1.0 - f64::EPSILON / 2.0)
+}
+
+// Sobol dimension 0 or 1 of index, as 32 bits of binary fraction.
+fn sobol(index: u32, dimension: u32) -> u32 {
+    if dimension == 0 {
+        return index.reverse_bits();
+    }
+    let mut index = index;
+    let mut result = 0;
+    let mut v = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn laine_karras_permutation(x: u32, seed: u32) -> u32 {
+    let mut x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+// Owen scrambling in base 2: each bit is flipped by a hash of the bits
+// above it.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn owen_sobol_2d(index: u64, seed: u64) -> (f64, f64) {
+    let index = nested_uniform_scramble(index as u32, seed as u32);
+    let x = nested_uniform_scramble(sobol(index, 0), (seed >> 32) as u32);
+    let y = nested_uniform_scramble(sobol(index, 1), mix64(seed) as u32);
+    let scale = 1.0 / (1u64 << 32) as f64;
+    (x as f64 * scale, y as f64 * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether the sampler puts exactly one of 16 samples in each of the 16
+    // strata of each dimension, and in each cell of a 4x4 grid.
+    fn is_stratified(sampler: &dyn Sampler) -> bool {
+        let mut strata = [0; 16];
+        let mut cells = [0; 16];
+        for index in 0..16 {
+            let sample = SampleIndex { i: 3, j: 7, index };
+            strata[(sampler.get_1d(&sample, 4) * 16.0) as usize] += 1;
+            let (x, y) = sampler.get_2d(&sample, 5);
+            cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] += 1;
+        }
+        strata.iter().chain(cells.iter()).all(|&count| count == 1)
+    }
+
+    #[test]
+    fn test_samplers() {
+        assert!(is_stratified(&StratifiedSampler::new(1, 16)));
+        assert!(is_stratified(&SobolSampler::new(1)));
+        let halton = HaltonSampler::new(1);
+        let sample = SampleIndex {
+            i: 0,
+            j: 0,
+            index: 5,
+        };
+        let (x, y) = halton.get_2d(&sample, 0);
+        assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+    }
+
+    #[test]
+    fn test_blue_noise_tile() {
+        let tile = blue_noise_tile(16, 1);
+        let mut ranks: Vec<usize> = tile.iter().map(|t| (t * 256.0) as usize).collect();
+        ranks.sort();
+        assert!(ranks == (0..256).collect::<Vec<_>>());
+    }
+}
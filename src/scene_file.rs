@@ -0,0 +1,776 @@
+use super::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Where the camera is and how it is focused.
+#[derive(Clone)]
+pub struct CameraDescription {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        Self {
+            lookfrom: Point3::new(0.0, 0.0, 1.0),
+            lookat: Point3::new(0.0, 0.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+        }
+    }
+}
+
+// Everything a scene file describes. Render settings it leaves out are None
+// and fall back to the command line defaults.
+pub struct SceneDescription {
+    pub objects: HitableList,
+    // Emitters flagged with "light": true, for next-event estimation.
+    pub lights: HitableList,
+    pub punctual_lights: Vec<Arc<dyn PunctualLight>>,
+    pub background: Box<dyn Background>,
+    pub camera: CameraDescription,
+    pub image_width: Option<i64>,
+    pub aspect_ratio: Option<f64>,
+    pub samples_per_pixel: Option<i64>,
+    pub max_depth: Option<i64>,
+    // The files the scene loaded assets from, in order.
+    pub files: Vec<String>,
+}
+
+impl SceneDescription {
+    // Command line defaults with the file's render settings in place.
+    pub fn settings(&self) -> Settings {
+        let mut settings: Settings = Default::default();
+        if let Some(width) = self.image_width {
+            settings.image_width = width;
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            settings.aspect_ratio = aspect_ratio;
+        }
+        if let Some(samples) = self.samples_per_pixel {
+            settings.samples_per_pixel = samples;
+        }
+        if let Some(depth) = self.max_depth {
+            settings.max_depth = depth;
+        }
+        settings
+    }
+}
+
+// Loads a JSON scene description. Relative asset paths are resolved against
+// the scene file's directory. See assets/example_scene.json for the format.
+pub fn load_scene(filename: &str) -> std::io::Result<SceneDescription> {
+    let source = std::fs::read_to_string(filename)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", filename, e)))?;
+    let base_dir = Path::new(filename)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    parse_scene(&source, base_dir).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {}", filename, e),
+        )
+    })
+}
+
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<SceneDescription, String> {
+    let doc = parse_json(source)?;
+    doc.as_object()?;
+    check_keys(
+        &doc,
+        &[
+            "render",
+            "camera",
+            "background",
+            "textures",
+            "materials",
+            "objects",
+            "lights",
+        ],
+        "scene",
+    )?;
+
+    let mut loader = SceneLoader {
+        base_dir: base_dir.to_path_buf(),
+        files: RefCell::new(Vec::new()),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+    };
+    let mut scene = SceneDescription {
+        objects: HitableList::new(),
+        lights: HitableList::new(),
+        punctual_lights: Vec::new(),
+        background: Box::new(GradientBackground::default()),
+        camera: Default::default(),
+        image_width: None,
+        aspect_ratio: None,
+        samples_per_pixel: None,
+        max_depth: None,
+        files: Vec::new(),
+    };
+
+    if let Some(render) = doc.get("render") {
+        check_keys(render, &["width", "aspect", "spp", "depth"], "render")?;
+        if let Some(v) = render.get("width") {
+            scene.image_width = Some(positive_integer(v)?);
+        }
+        if let Some(v) = render.get("aspect") {
+            let aspect = match &v.kind {
+                JsonKind::String(s) => {
+                    let parts: Vec<&str> = s.split(':').collect();
+                    match (
+                        parts.len(),
+                        parts[0].parse::<f64>(),
+                        parts.last().map(|h| h.parse::<f64>()),
+                    ) {
+                        (2, Ok(w), Some(Ok(h))) if h != 0.0 => w / h,
+                        _ => return Err(v.error(&format!("invalid aspect ratio \"{}\"", s))),
+                    }
+                }
+                _ => v.as_f64()?,
+            };
+            if aspect <= 0.0 {
+                return Err(v.error("aspect ratio must be positive"));
+            }
+            scene.aspect_ratio = Some(aspect);
+        }
+        if let Some(v) = render.get("spp") {
+            scene.samples_per_pixel = Some(positive_integer(v)?);
+        }
+        if let Some(v) = render.get("depth") {
+            scene.max_depth = Some(positive_integer(v)?);
+        }
+        let settings = scene.settings();
+        let (width, height) = (settings.image_width, settings.image_height());
+        if width as f64 * height as f64 > MAX_IMAGE_PIXELS as f64 {
+            return Err(render.error(&format!(
+                "a {}x{} image is too large: at most {} pixels are supported",
+                width, height, MAX_IMAGE_PIXELS
+            )));
+        }
+    }
+
+    if let Some(camera) = doc.get("camera") {
+        check_keys(
+            camera,
+            &[
+                "lookfrom",
+                "lookat",
+                "vup",
+                "vfov",
+                "aperture",
+                "focus_dist",
+            ],
+            "camera",
+        )?;
+        let c = &mut scene.camera;
+        if let Some(v) = camera.get("lookfrom") {
+            c.lookfrom = v.as_vec3()?;
+        }
+        if let Some(v) = camera.get("lookat") {
+            c.lookat = v.as_vec3()?;
+        }
+        if let Some(v) = camera.get("vup") {
+            c.vup = v.as_vec3()?;
+        }
+        if let Some(v) = camera.get("vfov") {
+            c.vfov = v.as_f64()?;
+            if c.vfov <= 0.0 || c.vfov >= 180.0 {
+                return Err(v.error("vfov must be between 0 and 180 degrees"));
+            }
+        }
+        if let Some(v) = camera.get("aperture") {
+            c.aperture = non_negative(v)?;
+        }
+        if let Some(v) = camera.get("focus_dist") {
+            c.focus_dist = positive(v)?;
+        }
+    }
+
+    if let Some(background) = doc.get("background") {
+        scene.background = loader.background(background)?;
+    }
+
+    // Textures and materials may refer to those defined before them.
+    if let Some(textures) = doc.get("textures") {
+        for (name, value) in textures.as_object()? {
+            let texture = loader.texture(value)?;
+            loader.textures.insert(name.clone(), texture);
+        }
+    }
+    if let Some(materials) = doc.get("materials") {
+        for (name, value) in materials.as_object()? {
+            let material = loader.material(value)?;
+            loader.materials.insert(name.clone(), material);
+        }
+    }
+
+    if let Some(objects) = doc.get("objects") {
+        for value in objects.as_array()? {
+            let object = loader.object(value)?;
+            if let Some(light) = value.get("light") {
+                if light.as_bool()? {
+                    // Lights are sampled by direction, which these types
+                    // do not support, so they would only skew the estimate.
+                    let kind = type_of(value)?;
+                    if ["mesh", "moving_sphere", "constant_medium"].contains(&kind) {
+                        return Err(light.error(&format!("a {} cannot be a light", kind)));
+                    }
+                    scene.lights.add(object.clone());
+                }
+            }
+            scene.objects.add(object);
+        }
+    }
+    if scene.objects.objects.is_empty() {
+        return Err(doc.error("scene has no objects"));
+    }
+
+    if let Some(lights) = doc.get("lights") {
+        for value in lights.as_array()? {
+            scene.punctual_lights.push(loader.punctual_light(value)?);
+        }
+    }
+
+    scene.files = loader.files.into_inner();
+    Ok(scene)
+}
+
+struct SceneLoader {
+    base_dir: PathBuf,
+    files: RefCell<Vec<String>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+}
+
+impl SceneLoader {
+    // Resolves an asset path and records it in files.
+    fn path(&self, value: &JsonValue) -> Result<String, String> {
+        let path = self
+            .base_dir
+            .join(value.as_str()?)
+            .to_string_lossy()
+            .into_owned();
+        self.files.borrow_mut().push(path.clone());
+        Ok(path)
+    }
+
+    fn background(&self, value: &JsonValue) -> Result<Box<dyn Background>, String> {
+        Ok(match type_of(value)? {
+            "solid" => {
+                check_keys(value, &["type", "color"], "solid background")?;
+                Box::new(SolidBackground::new(required(value, "color")?.as_vec3()?))
+            }
+            "gradient" => {
+                check_keys(value, &["type", "bottom", "top"], "gradient background")?;
+                let mut gradient = GradientBackground::default();
+                if let Some(v) = value.get("bottom") {
+                    gradient.bottom = v.as_vec3()?;
+                }
+                if let Some(v) = value.get("top") {
+                    gradient.top = v.as_vec3()?;
+                }
+                Box::new(gradient)
+            }
+            "environment" => {
+                check_keys(
+                    value,
+                    &["type", "file", "rotation", "intensity"],
+                    "environment background",
+                )?;
+                let file = required(value, "file")?;
+                let rotation = optional_f64(value, "rotation", 0.0)?;
+                let intensity = optional_f64(value, "intensity", 1.0)?;
+                let map = EnvironmentMap::load(&self.path(file)?, rotation, intensity)
+                    .map_err(|e| file.error(&e.to_string()))?;
+                Box::new(map)
+            }
+            "sky" => {
+                check_keys(
+                    value,
+                    &["type", "elevation", "azimuth", "turbidity", "intensity"],
+                    "sky background",
+                )?;
+                Box::new(SunSky::new(
+                    optional_f64(value, "elevation", DEFAULT_SUN_ELEVATION)?,
+                    optional_f64(value, "azimuth", DEFAULT_SUN_AZIMUTH)?,
+                    optional_f64(value, "turbidity", DEFAULT_TURBIDITY)?,
+                    optional_f64(value, "intensity", DEFAULT_SKY_INTENSITY)?,
+                ))
+            }
+            other => return Err(value.error(&format!("unknown background type \"{}\"", other))),
+        })
+    }
+
+    // A texture name, an [r, g, b] color, or an inline texture definition.
+    fn texture_ref(&self, value: &JsonValue) -> Result<Arc<dyn Texture>, String> {
+        match &value.kind {
+            JsonKind::String(name) => match self.textures.get(name) {
+                Some(texture) => Ok(texture.clone()),
+                None => Err(value.error(&format!("unknown texture \"{}\"", name))),
+            },
+            JsonKind::Array(_) => Ok(Arc::new(SolidColor::new(value.as_vec3()?))),
+            _ => self.texture(value),
+        }
+    }
+
+    fn texture(&self, value: &JsonValue) -> Result<Arc<dyn Texture>, String> {
+        Ok(match type_of(value)? {
+            "solid" => {
+                check_keys(value, &["type", "color"], "solid texture")?;
+                Arc::new(SolidColor::new(required(value, "color")?.as_vec3()?))
+            }
+            "checker" => {
+                check_keys(value, &["type", "even", "odd"], "checker texture")?;
+                Arc::new(CheckerTexture::new(
+                    self.texture_ref(required(value, "even")?)?,
+                    self.texture_ref(required(value, "odd")?)?,
+                ))
+            }
+            "image" => {
+                check_keys(value, &["type", "file"], "image texture")?;
+                let file = required(value, "file")?;
+                let texture =
+                    ImageTexture::new(&self.path(file)?).map_err(|e| file.error(&e.to_string()))?;
+                Arc::new(texture)
+            }
+            // Noise textures take an optional "seed" that fixes their pattern;
+            // without one it follows the render's seed.
+            "noise" => {
+                check_keys(value, &["type", "scale", "seed"], "noise texture")?;
+                let scale = optional_f64(value, "scale", 1.0)?;
+                match value.get("seed") {
+                    Some(v) => Arc::new(NoiseTexture::with_seed(scale, noise_seed(v)?)),
+                    None => Arc::new(NoiseTexture::new(scale)),
+                }
+            }
+            "turbulence" => {
+                check_keys(
+                    value,
+                    &["type", "scale", "depth", "seed"],
+                    "turbulence texture",
+                )?;
+                let scale = optional_f64(value, "scale", 1.0)?;
+                let depth = match value.get("depth") {
+                    Some(v) => positive_integer(v)?,
+                    None => 7,
+                };
+                match value.get("seed") {
+                    Some(v) => Arc::new(TurbulenceTexture::with_seed(scale, depth, noise_seed(v)?)),
+                    None => Arc::new(TurbulenceTexture::new(scale, depth)),
+                }
+            }
+            "marble" => {
+                check_keys(value, &["type", "scale", "color", "seed"], "marble texture")?;
+                let scale = optional_f64(value, "scale", 1.0)?;
+                let color = match value.get("color") {
+                    Some(v) => v.as_vec3()?,
+                    None => Color::new(1.0, 1.0, 1.0),
+                };
+                match value.get("seed") {
+                    Some(v) => Arc::new(MarbleTexture::with_seed(scale, color, noise_seed(v)?)),
+                    None => Arc::new(MarbleTexture::new(scale, color)),
+                }
+            }
+            other => return Err(value.error(&format!("unknown texture type \"{}\"", other))),
+        })
+    }
+
+    // A material name or an inline material definition.
+    fn material_ref(&self, value: &JsonValue) -> Result<Arc<dyn Material>, String> {
+        match &value.kind {
+            JsonKind::String(name) => match self.materials.get(name) {
+                Some(material) => Ok(material.clone()),
+                None => Err(value.error(&format!("unknown material \"{}\"", name))),
+            },
+            _ => self.material(value),
+        }
+    }
+
+    fn material(&self, value: &JsonValue) -> Result<Arc<dyn Material>, String> {
+        Ok(match type_of(value)? {
+            "lambertian" => {
+                check_keys(value, &["type", "albedo"], "lambertian material")?;
+                Arc::new(Lambertian::from_texture(
+                    self.texture_ref(required(value, "albedo")?)?,
+                ))
+            }
+            "metal" => {
+                check_keys(value, &["type", "albedo", "fuzz"], "metal material")?;
+                Arc::new(Metal::from_texture(
+                    self.texture_ref(required(value, "albedo")?)?,
+                    optional_f64(value, "fuzz", 0.0)?,
+                ))
+            }
+            "dielectric" => {
+                check_keys(value, &["type", "ior"], "dielectric material")?;
+                Arc::new(Dielectric::new(positive(required(value, "ior")?)?))
+            }
+            "diffuse_light" => {
+                check_keys(value, &["type", "emit"], "diffuse_light material")?;
+                Arc::new(DiffuseLight::from_texture(
+                    self.texture_ref(required(value, "emit")?)?,
+                ))
+            }
+            "isotropic" => {
+                check_keys(value, &["type", "albedo"], "isotropic material")?;
+                Arc::new(Isotropic::from_texture(
+                    self.texture_ref(required(value, "albedo")?)?,
+                ))
+            }
+            other => return Err(value.error(&format!("unknown material type \"{}\"", other))),
+        })
+    }
+
+    fn object(&self, value: &JsonValue) -> Result<Arc<dyn Hitable>, String> {
+        // Keys every object accepts besides its own.
+        const COMMON: [&str; 3] = ["type", "transform", "light"];
+        let keys = |own: &[&'static str]| -> Vec<&'static str> {
+            COMMON.iter().chain(own).copied().collect()
+        };
+
+        let kind = type_of(value)?;
+        let object: Arc<dyn Hitable> = match kind {
+            "sphere" => {
+                check_keys(value, &keys(&["center", "radius", "material"]), kind)?;
+                Arc::new(Sphere::new(
+                    required(value, "center")?.as_vec3()?,
+                    required(value, "radius")?.as_f64()?,
+                    self.material_ref(required(value, "material")?)?,
+                ))
+            }
+            "moving_sphere" => {
+                check_keys(
+                    value,
+                    &keys(&["center0", "center1", "time0", "time1", "radius", "material"]),
+                    kind,
+                )?;
+                Arc::new(MovingSphere::new(
+                    required(value, "center0")?.as_vec3()?,
+                    required(value, "center1")?.as_vec3()?,
+                    optional_f64(value, "time0", 0.0)?,
+                    optional_f64(value, "time1", 1.0)?,
+                    required(value, "radius")?.as_f64()?,
+                    self.material_ref(required(value, "material")?)?,
+                ))
+            }
+            "quad" => {
+                check_keys(value, &keys(&["q", "u", "v", "material"]), kind)?;
+                Arc::new(Quad::new(
+                    required(value, "q")?.as_vec3()?,
+                    required(value, "u")?.as_vec3()?,
+                    required(value, "v")?.as_vec3()?,
+                    self.material_ref(required(value, "material")?)?,
+                ))
+            }
+            "box" => {
+                check_keys(value, &keys(&["min", "max", "material"]), kind)?;
+                Arc::new(Cuboid::new(
+                    &required(value, "min")?.as_vec3()?,
+                    &required(value, "max")?.as_vec3()?,
+                    self.material_ref(required(value, "material")?)?,
+                ))
+            }
+            "triangle" => {
+                check_keys(value, &keys(&["vertices", "normals", "material"]), kind)?;
+                let vertices = triangle_vectors(required(value, "vertices")?, "vertices")?;
+                let material = self.material_ref(required(value, "material")?)?;
+                // Optional per-vertex normals, interpolated for smooth shading.
+                match value.get("normals") {
+                    Some(normals) => {
+                        let normals_array = triangle_vectors(normals, "normals")?;
+                        if normals_array.iter().any(|n| n.length_squared() == 0.0) {
+                            return Err(normals.error("vertex normals must be non-zero"));
+                        }
+                        Arc::new(Triangle::with_normals(vertices, normals_array, material))
+                    }
+                    None => {
+                        let [p0, p1, p2] = vertices;
+                        Arc::new(Triangle::new(p0, p1, p2, material))
+                    }
+                }
+            }
+            "mesh" => {
+                check_keys(value, &keys(&["file", "material", "materials"]), kind)?;
+                let default_material = match value.get("material") {
+                    Some(v) => self.material_ref(v)?,
+                    None => Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+                };
+                // Maps the mesh's usemtl names to scene materials.
+                let mut materials = HashMap::new();
+                if let Some(v) = value.get("materials") {
+                    for (name, material) in v.as_object()? {
+                        materials.insert(name.clone(), self.material_ref(material)?);
+                    }
+                }
+                let file = required(value, "file")?;
+                load_obj(&self.path(file)?, &materials, default_material)
+                    .map_err(|e| file.error(&e.to_string()))?
+            }
+            "constant_medium" => {
+                check_keys(value, &keys(&["boundary", "density", "albedo"]), kind)?;
+                Arc::new(ConstantMedium::from_texture(
+                    self.object(required(value, "boundary")?)?,
+                    positive(required(value, "density")?)?,
+                    self.texture_ref(required(value, "albedo")?)?,
+                ))
+            }
+            other => return Err(value.error(&format!("unknown object type \"{}\"", other))),
+        };
+
+        match value.get("transform") {
+            Some(transform) => match Transformed::new(object, self.transform(transform)?) {
+                Some(transformed) => Ok(Arc::new(transformed)),
+                None => Err(transform.error("transform cannot be inverted")),
+            },
+            None => Ok(object),
+        }
+    }
+
+    // A list of steps such as {"rotate_y": 15} or {"translate": [1, 0, 0]},
+    // applied to the object in order.
+    fn transform(&self, value: &JsonValue) -> Result<Transform, String> {
+        let mut transform = Transform::identity();
+        for step in value.as_array()? {
+            let members = step.as_object()?;
+            if members.len() != 1 {
+                return Err(step.error("each transform step needs exactly one operation"));
+            }
+            let (op, arg) = (&members[0].0, &members[0].1);
+            let t = match op.as_str() {
+                "translate" => Transform::translate(&arg.as_vec3()?),
+                "scale" => {
+                    let factors = match arg.kind {
+                        JsonKind::Number(s) => Vec3::new(s, s, s),
+                        _ => arg.as_vec3()?,
+                    };
+                    if factors.x() * factors.y() * factors.z() == 0.0 {
+                        return Err(arg.error("scale factors must be non-zero"));
+                    }
+                    Transform::scale(&factors)
+                }
+                "rotate_x" => Transform::rotate_x(arg.as_f64()?),
+                "rotate_y" => Transform::rotate_y(arg.as_f64()?),
+                "rotate_z" => Transform::rotate_z(arg.as_f64()?),
+                "rotate" => {
+                    check_keys(arg, &["axis", "degrees"], "rotate")?;
+                    let axis = required(arg, "axis")?;
+                    let direction = axis.as_vec3()?;
+                    if direction.length_squared() == 0.0 {
+                        return Err(axis.error("rotation axis must be non-zero"));
+                    }
+                    Transform::rotate(&direction, required(arg, "degrees")?.as_f64()?)
+                }
+                _ => return Err(step.error(&format!("unknown transform operation \"{}\"", op))),
+            };
+            transform = &t * &transform;
+        }
+        Ok(transform)
+    }
+
+    fn punctual_light(&self, value: &JsonValue) -> Result<Arc<dyn PunctualLight>, String> {
+        Ok(match type_of(value)? {
+            "point" => {
+                check_keys(value, &["type", "position", "intensity"], "point light")?;
+                Arc::new(PointLight::new(
+                    required(value, "position")?.as_vec3()?,
+                    required(value, "intensity")?.as_vec3()?,
+                ))
+            }
+            "spot" => {
+                check_keys(
+                    value,
+                    &[
+                        "type",
+                        "position",
+                        "target",
+                        "intensity",
+                        "total_width",
+                        "falloff_start",
+                    ],
+                    "spot light",
+                )?;
+                let total_width = optional_f64(value, "total_width", 30.0)?;
+                Arc::new(SpotLight::new(
+                    required(value, "position")?.as_vec3()?,
+                    required(value, "target")?.as_vec3()?,
+                    required(value, "intensity")?.as_vec3()?,
+                    total_width,
+                    optional_f64(value, "falloff_start", total_width)?,
+                ))
+            }
+            "directional" => {
+                check_keys(
+                    value,
+                    &["type", "direction", "irradiance"],
+                    "directional light",
+                )?;
+                Arc::new(DirectionalLight::new(
+                    required(value, "direction")?.as_vec3()?,
+                    required(value, "irradiance")?.as_vec3()?,
+                ))
+            }
+            other => return Err(value.error(&format!("unknown light type \"{}\"", other))),
+        })
+    }
+}
+
+fn type_of(value: &JsonValue) -> Result<&str, String> {
+    required(value, "type")?.as_str()
+}
+
+fn required<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, String> {
+    value.as_object()?;
+    value
+        .get(key)
+        .ok_or_else(|| value.error(&format!("missing \"{}\"", key)))
+}
+
+fn optional_f64(value: &JsonValue, key: &str, default: f64) -> Result<f64, String> {
+    match value.get(key) {
+        Some(v) => v.as_f64(),
+        None => Ok(default),
+    }
+}
+
+fn positive(value: &JsonValue) -> Result<f64, String> {
+    let x = value.as_f64()?;
+    if x <= 0.0 {
+        return Err(value.error("expected a positive number"));
+    }
+    Ok(x)
+}
+
+fn non_negative(value: &JsonValue) -> Result<f64, String> {
+    let x = value.as_f64()?;
+    if x < 0.0 {
+        return Err(value.error("expected a non-negative number"));
+    }
+    Ok(x)
+}
+
+fn positive_integer(value: &JsonValue) -> Result<i64, String> {
+    let x = value.as_f64()?;
+    if x < 1.0 || x.fract() != 0.0 {
+        return Err(value.error("expected a positive integer"));
+    }
+    Ok(x as i64)
+}
+
+// A triangle's three vertices or vertex normals.
+fn triangle_vectors(value: &JsonValue, what: &str) -> Result<[Vec3; 3], String> {
+    match value.as_array()? {
+        [a, b, c] => Ok([a.as_vec3()?, b.as_vec3()?, c.as_vec3()?]),
+        _ => Err(value.error(&format!("a triangle needs three {}", what))),
+    }
+}
+
+fn noise_seed(value: &JsonValue) -> Result<u32, String> {
+    let x = value.as_f64()?;
+    if !(0.0..=u32::MAX as f64).contains(&x) || x.fract() != 0.0 {
+        return Err(value.error("expected an integer seed from 0 to 4294967295"));
+    }
+    Ok(x as u32)
+}
+
+// Rejects misspelled keys rather than silently ignoring them.
+fn check_keys(value: &JsonValue, allowed: &[&str], what: &str) -> Result<(), String> {
+    for (key, member) in value.as_object()? {
+        if !allowed.contains(&key.as_str()) {
+            return Err(member.error(&format!("unknown key \"{}\" in {}", key, what)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scene() {
+        let source = r#"{
+  "render": {"width": 320, "aspect": "4:3"},
+  "camera": {"lookfrom": [0, 1, 5], "vfov": 30},
+  "textures": {"stone": {"type": "marble", "scale": 2, "seed": 7}},
+  "materials": {
+    "white": {"type": "lambertian", "albedo": [0.7, 0.7, 0.7]},
+    "lamp": {"type": "diffuse_light", "emit": [4, 4, 4]}
+  },
+  "objects": [
+    {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "white"},
+    {"type": "triangle", "vertices": [[-1, 0, 0], [1, 0, 0], [0, 1, 0]],
+     "normals": [[-1, 0, 1], [1, 0, 1], [0, 0, 1]], "material": "white"},
+    {"type": "quad", "q": [-1, 3, -1], "u": [2, 0, 0], "v": [0, 0, 2],
+     "material": "lamp", "light": true,
+     "transform": [{"rotate_y": 45}, {"translate": [0, 1, 0]}]}
+  ],
+  "lights": [{"type": "point", "position": [0, 5, 0], "intensity": [1, 1, 1]}]
+}"#;
+        let scene = parse_scene(source, Path::new("")).unwrap();
+        assert!(scene.objects.objects.len() == 3 && scene.lights.objects.len() == 1);
+        assert!(scene.punctual_lights.len() == 1);
+        assert!(scene.image_width == Some(320) && scene.settings().image_height() == 240);
+        assert!(scene.camera.lookfrom.z() == 5.0 && scene.camera.vfov == 30.0);
+
+        // The triangle's vertex normals tilt its shading normal.
+        let mut rec: HitRecord = Default::default();
+        let r = Ray::new(Point3::new(-0.5, 0.25, 1.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(scene.objects.objects[1].hit(&r, 0.001, INFINITY, &mut rec));
+        assert!(rec.normal.x() < -0.3);
+    }
+
+    #[test]
+    fn test_parse_scene_errors() {
+        let error = |source: &str| parse_scene(source, Path::new("")).err().unwrap();
+        assert!(
+            error("{\n  \"objects\": [\n    {\"type\": \"sphere\", \"center\": [0, 0, 0],\n     \"radius\": 1, \"material\": \"chrome\"}\n  ]\n}")
+                == "line 4: unknown material \"chrome\""
+        );
+        assert!(
+            error("{\"objects\": [\n  {\"type\": \"sphere\", \"radius\": 1}\n]}")
+                == "line 2: missing \"center\""
+        );
+        assert!(
+            error("{\n  \"camera\": {\n    \"fov\": 40\n  }\n}")
+                == "line 3: unknown key \"fov\" in camera"
+        );
+        assert!(error("{\"objects\": []}") == "line 1: scene has no objects");
+        assert!(
+            error(
+                "{\"objects\": [{\"type\": \"moving_sphere\", \"center0\": [0, 0, 0],\n\
+                 \"center1\": [0, 1, 0], \"radius\": 1, \"material\": {\"type\": \"lambertian\",\n\
+                 \"albedo\": [1, 1, 1]}, \"light\": true}]}"
+            ) == "line 3: a moving_sphere cannot be a light"
+        );
+        assert!(error("{\"render\": {\"width\": 1e10, \"aspect\": 1}}")
+            .starts_with("line 1: a 10000000000x10000000000 image is too large"));
+
+        let transformed = |step: &str| {
+            error(&format!(
+                "{{\"objects\": [{{\"type\": \"sphere\", \"center\": [0, 0, 0], \"radius\": 1,\n\
+                 \"material\": {{\"type\": \"lambertian\", \"albedo\": [1, 1, 1]}}, \"transform\": [{}]}}]}}",
+                step
+            ))
+        };
+        assert!(
+            transformed("{\"rotate\": {\"axis\": [0, 0, 0], \"degrees\": 30}}")
+                == "line 2: rotation axis must be non-zero"
+        );
+        assert!(transformed("{\"scale\": 1e-100}") == "line 2: transform cannot be inverted");
+
+        let missing = error(
+            "{\"textures\": {\"earth\": {\"type\": \"image\",\n\
+             \"file\": \"no_such_texture.png\"}}}",
+        );
+        assert!(missing.starts_with("line 2: no_such_texture.png: "));
+    }
+}
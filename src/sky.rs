@@ -0,0 +1,229 @@
+use super::*;
+
+// Angular radius of the sun disk as seen from the ground.
+pub const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+// Luminance of the sun above the atmosphere, in the kcd/m^2 units of the
+// Preetham sky luminance.
+pub const SUN_LUMINANCE: f64 = 1.6e6;
+
+// Coefficients of the Perez sky distribution function.
+#[derive(Clone, Copy)]
+pub struct PerezCoefficients {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+}
+
+impl PerezCoefficients {
+    // Relative luminance for a view angle theta from the zenith and an angle
+    // gamma from the sun.
+    pub fn f(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+// Sky defaults shared by the command line and scene files.
+pub const DEFAULT_SUN_ELEVATION: f64 = 35.0;
+pub const DEFAULT_SUN_AZIMUTH: f64 = 60.0;
+pub const DEFAULT_TURBIDITY: f64 = 3.0;
+pub const DEFAULT_SKY_INTENSITY: f64 = 0.02;
+
+// Preetham, Shirley and Smits' analytic daylight model with a sun disk.
+// Elevation is in degrees above the horizon and azimuth in degrees about +y
+// from the +x axis towards -z, matching EnvironmentMap. Turbidity runs from
+// about 2 (very clear) to 10 (hazy).
+pub struct SunSky {
+    pub sun_direction: Vec3,
+    pub intensity: f64,
+    pub perez: [PerezCoefficients; 3],
+    // Zenith luminance Y and chromaticity x, y.
+    pub zenith: [f64; 3],
+    // Perez function towards the zenith, which normalizes the distribution.
+    pub zenith_f: [f64; 3],
+    pub sun_radiance: Color,
+    pub cos_sun_radius: f64,
+}
+
+impl SunSky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Self {
+        let el = degrees_to_radians(elevation);
+        let az = degrees_to_radians(azimuth);
+        let sun_direction = Vec3::new(el.cos() * az.cos(), el.sin(), -el.cos() * az.sin());
+
+        // The model is only fitted for the sun above the horizon.
+        let theta_s = (PI / 2.0 - el).clamp(0.0, PI / 2.0);
+        let t = turbidity;
+
+        let perez = [
+            PerezCoefficients {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            PerezCoefficients {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            PerezCoefficients {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic = |c: [f64; 4]| ((c[0] * theta_s + c[1]) * theta_s + c[2]) * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_yc = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let zenith_f = [
+            perez[0].f(1.0, theta_s),
+            perez[1].f(1.0, theta_s),
+            perez[2].f(1.0, theta_s),
+        ];
+
+        let sun_radiance = if elevation > 0.0 {
+            SUN_LUMINANCE * &sun_transmittance(theta_s, t)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+
+        Self {
+            sun_direction,
+            intensity,
+            perez,
+            zenith: [zenith_y.max(0.0), zenith_x, zenith_yc],
+            zenith_f,
+            sun_radiance,
+            cos_sun_radius: SUN_ANGULAR_RADIUS.cos(),
+        }
+    }
+
+    fn sun_visible(&self) -> bool {
+        self.sun_radiance.length_squared() > 0.0
+    }
+
+    // Sky radiance without the sun disk. Directions below the horizon see
+    // the sky at the horizon.
+    pub fn sky_radiance(&self, direction: &Vec3) -> Color {
+        let d = unit_vector(direction);
+        let cos_theta = d.y().max(0.001);
+        let gamma = clamp(dot(&d, &self.sun_direction), -1.0, 1.0).acos();
+
+        let mut yxy = [0.0; 3];
+        for (i, value) in yxy.iter_mut().enumerate() {
+            *value = self.zenith[i] * self.perez[i].f(cos_theta, gamma) / self.zenith_f[i];
+        }
+        self.intensity * &yxy_to_rgb(yxy[0], yxy[1], yxy[2])
+    }
+}
+
+impl Background for SunSky {
+    fn value(&self, r: &Ray) -> Color {
+        let mut color = self.sky_radiance(r.direction());
+        if self.sun_visible()
+            && dot(&unit_vector(r.direction()), &self.sun_direction) >= self.cos_sun_radius
+        {
+            color += self.intensity * &self.sun_radiance;
+        }
+        color
+    }
+
+    fn is_sampleable(&self) -> bool {
+        true
+    }
+
+    // An even mix of the sun's cone and a cosine lobe around the zenith for
+    // the sky. With the sun down only the sky lobe remains.
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let d = unit_vector(direction);
+        let sky_pdf = d.y().max(0.0) / PI;
+        if !self.sun_visible() {
+            return sky_pdf;
+        }
+        let sun_pdf = if dot(&d, &self.sun_direction) >= self.cos_sun_radius {
+            1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+        } else {
+            0.0
+        };
+        0.5 * sun_pdf + 0.5 * sky_pdf
+    }
+
+    fn random(&self) -> Vec3 {
+        if self.sun_visible() && sample_1d() < 0.5 {
+            let uvw = Onb::build_from_w(&self.sun_direction);
+            uvw.local_v(&random_to_sphere(SUN_ANGULAR_RADIUS.sin(), 1.0))
+        } else {
+            Onb::build_from_w(&Vec3::new(0.0, 1.0, 0.0)).local_v(&random_cosine_direction())
+        }
+    }
+}
+
+// Fraction of sunlight reaching the ground through Rayleigh and aerosol
+// scattering, evaluated at a representative wavelength for each channel.
+pub fn sun_transmittance(theta_s: f64, turbidity: f64) -> Color {
+    // Relative optical mass of the atmosphere along the sun's direction.
+    let theta_degrees = theta_s * 180.0 / PI;
+    let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let channel = |lambda: f64| {
+        let rayleigh = (-m * 0.008735 * lambda.powf(-4.08)).exp();
+        let aerosol = (-m * beta * lambda.powf(-1.3)).exp();
+        rayleigh * aerosol
+    };
+    Color::new(channel(0.68), channel(0.55), channel(0.44))
+}
+
+// CIE Yxy to linear sRGB primaries.
+pub fn yxy_to_rgb(big_y: f64, x: f64, y: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * big_y;
+    let big_z = (1.0 - x - y) / y * big_y;
+    // Clamp colors outside the sRGB gamut.
+    Color::new(
+        (3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_sky() {
+        let sky = SunSky::new(30.0, 90.0, 3.0, 1.0);
+        assert!((sky.sun_direction.z() + 30.0_f64.to_radians().cos()).abs() < 1e-9);
+
+        // The Perez function is normalized to the zenith luminance.
+        let zenith = sky.sky_radiance(&Vec3::new(0.0, 1.0, 0.0));
+        assert!((luminance(&zenith) - sky.zenith[0]).abs() < 0.01 * sky.zenith[0]);
+
+        // Looking at the sun is far brighter than anywhere else in the sky.
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), sky.sun_direction.clone(), 0.0);
+        assert!(luminance(&sky.value(&r)) > 1000.0 * luminance(&zenith));
+
+        let night = SunSky::new(-10.0, 0.0, 3.0, 1.0);
+        assert!(night.pdf_value(&night.random()) > 0.0);
+    }
+}
@@ -1,14 +1,14 @@
 use super::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
-    pub mat_ptr: Rc<dyn Material>,
+    pub mat_ptr: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f64, mat_ptr: Rc<dyn Material>) -> Self {
+    pub fn new(center: Vec3, radius: f64, mat_ptr: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius,
@@ -17,6 +17,21 @@ impl Sphere {
     }
 }
 
+pub fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) {
+    // p: a given point on the sphere of radius one, centered at the origin.
+    // u: returned value [0,1] of angle around the Y axis from X=-1.
+    // v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    //     <1 0 0> yields <0.50 0.50>       <-1  0  0> yields <0.00 0.50>
+    //     <0 1 0> yields <0.50 1.00>       < 0 -1  0> yields <0.50 0.00>
+    //     <0 0 1> yields <0.25 0.50>       < 0  0 -1> yields <0.75 0.50>
+
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+
+    *u = phi / (2.0 * PI);
+    *v = theta / PI;
+}
+
 impl Hitable for Sphere {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
         let oc = r.origin() - &self.center;
@@ -33,6 +48,7 @@ impl Hitable for Sphere {
                 rec.p = r.at(rec.t);
                 let outward_normal = &(&rec.p - &self.center) / self.radius;
                 rec.set_face_normal(r, &outward_normal);
+                get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
                 rec.mat_ptr = self.mat_ptr.clone();
                 return true;
             }
@@ -42,10 +58,68 @@ impl Hitable for Sphere {
                 rec.p = r.at(rec.t);
                 let outward_normal = &(&rec.p - &self.center) / self.radius;
                 rec.set_face_normal(r, &outward_normal);
+                get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
                 rec.mat_ptr = self.mat_ptr.clone();
                 return true;
             }
         }
         false
     }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let r = Vec3(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        output_box.assign(&Aabb::new(&self.center - &r, &self.center + &r));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let mut rec: HitRecord = Default::default();
+        if !self.hit(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            0.001,
+            INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+
+        let distance_squared = (&self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            // Inside the sphere every direction hits it; random() samples uniformly.
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let direction = &self.center - origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return random_unit_vector();
+        }
+        let uvw = Onb::build_from_w(&direction);
+        uvw.local_v(&random_to_sphere(self.radius, distance_squared))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_sphere_uv() {
+        let (mut u, mut v) = (0.0, 0.0);
+        get_sphere_uv(&Point3::new(1.0, 0.0, 0.0), &mut u, &mut v);
+        assert!(u == 0.5 && v == 0.5);
+        get_sphere_uv(&Point3::new(0.0, 1.0, 0.0), &mut u, &mut v);
+        assert!(v == 1.0);
+        get_sphere_uv(&Point3::new(0.0, 0.0, 1.0), &mut u, &mut v);
+        assert!(u == 0.25 && v == 0.5);
+        get_sphere_uv(&Point3::new(0.0, 0.0, -1.0), &mut u, &mut v);
+        assert!(u == 0.75 && v == 0.5);
+    }
 }
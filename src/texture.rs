@@ -0,0 +1,207 @@
+use super::*;
+use std::sync::Arc;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    pub color_value: Color,
+}
+
+impl SolidColor {
+    pub fn new(c: Color) -> Self {
+        Self { color_value: c }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color_value.clone()
+    }
+}
+
+pub struct CheckerTexture {
+    pub odd: Arc<dyn Texture>,
+    pub even: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { odd, even }
+    }
+
+    pub fn from_colors(c1: Color, c2: Color) -> Self {
+        Self::new(Arc::new(SolidColor::new(c1)), Arc::new(SolidColor::new(c2)))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let sines = (10.0 * p.x()).sin() * (10.0 * p.y()).sin() * (10.0 * p.z()).sin();
+        if sines < 0.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
+
+// An 8-bit sRGB image, decoded to linear colors when loaded.
+pub struct ImageTexture {
+    pub data: Vec<Color>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl ImageTexture {
+    pub fn new(filename: &str) -> std::io::Result<Self> {
+        let error = |kind, e: &dyn std::fmt::Display| {
+            std::io::Error::new(kind, format!("{}: {}", filename, e))
+        };
+        let img = image::open(filename).map_err(|e| match e {
+            image::ImageError::IoError(e) => error(e.kind(), &e),
+            e => error(std::io::ErrorKind::InvalidData, &e),
+        })?;
+        let img = img.to_rgb8();
+        if img.width() == 0 || img.height() == 0 {
+            return Err(error(std::io::ErrorKind::InvalidData, &"empty image"));
+        }
+        Ok(Self::from_srgb8(
+            img.width() as usize,
+            img.height() as usize,
+            img.as_raw(),
+        ))
+    }
+
+    // From rows of 8-bit sRGB triples, top row first.
+    pub fn from_srgb8(width: usize, height: usize, bytes: &[u8]) -> Self {
+        let decode = |x: u8| srgb_to_linear(x as f64 / 255.0);
+        Self {
+            data: bytes
+                .chunks(3)
+                .map(|p| Color::new(decode(p[0]), decode(p[1]), decode(p[2])))
+                .collect(),
+            width,
+            height,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // Clamp input texture coordinates to [0,1] x [1,0]
+        let u = clamp(u, 0.0, 1.0);
+        let v = 1.0 - clamp(v, 0.0, 1.0); // Flip V to image coordinates
+
+        // Clamp integer mapping, since actual coordinates should be less than 1.0
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.data[j * self.width + i].clone()
+    }
+}
+
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+
+    pub fn with_seed(scale: f64, seed: u32) -> Self {
+        Self {
+            noise: Perlin::with_seed(seed),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        0.5 * (1.0 + self.noise.noise(&(self.scale * p))) * &Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+pub struct TurbulenceTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub depth: i64,
+}
+
+impl TurbulenceTexture {
+    pub fn new(scale: f64, depth: i64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            depth,
+        }
+    }
+
+    pub fn with_seed(scale: f64, depth: i64, seed: u32) -> Self {
+        Self {
+            noise: Perlin::with_seed(seed),
+            scale,
+            depth,
+        }
+    }
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        self.noise.turb(&(self.scale * p), self.depth) * &Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+// Veins come from a sine wave along z whose phase is perturbed by turbulence.
+pub struct MarbleTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub color: Color,
+}
+
+impl MarbleTexture {
+    pub fn new(scale: f64, color: Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            color,
+        }
+    }
+
+    pub fn with_seed(scale: f64, color: Color, seed: u32) -> Self {
+        Self {
+            noise: Perlin::with_seed(seed),
+            scale,
+            color,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        0.5 * (1.0 + (self.scale * p.z() + 10.0 * self.noise.turb(p, 7)).sin()) * &self.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_texture_is_linear() {
+        let texture = ImageTexture::from_srgb8(2, 1, &[0, 128, 255, 188, 188, 188]);
+        let p = Point3::new(0.0, 0.0, 0.0);
+        let left = texture.value(0.0, 0.5, &p);
+        assert!(left.x() == 0.0 && left.z() == 1.0);
+        assert!((left.y() - 0.2158).abs() < 1e-3);
+        assert!((texture.value(1.0, 0.5, &p).x() - 0.5029).abs() < 1e-3);
+        assert!(ImageTexture::new("no/such/texture.png").is_err());
+    }
+}
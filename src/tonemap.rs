@@ -0,0 +1,108 @@
+use super::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapOperator {
+    // Clip each channel to [0,1].
+    Clamp,
+    // L / (1 + L) on luminance, which keeps hues but never reaches white.
+    Reinhard,
+    // Reinhard with the given luminance mapping to white.
+    ExtendedReinhard(f64),
+    // Narkowicz's fit of the ACES filmic reference curve.
+    Aces,
+    // John Hable's Uncharted 2 filmic curve.
+    Hable,
+}
+
+// Maps linear scene radiance to linear display values in [0,1]. Exposure is
+// in stops, so +1 doubles the radiance before the operator is applied.
+#[derive(Clone, Copy)]
+pub struct ToneMapper {
+    pub operator: ToneMapOperator,
+    pub exposure: f64,
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        Self::new(ToneMapOperator::Clamp, 0.0)
+    }
+}
+
+impl ToneMapper {
+    pub fn new(operator: ToneMapOperator, exposure: f64) -> Self {
+        Self { operator, exposure }
+    }
+
+    pub fn map(&self, c: &Color) -> Color {
+        let c = 2.0_f64.powf(self.exposure) * c;
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => c,
+            ToneMapOperator::Reinhard => scale_luminance(&c, |l| l / (1.0 + l)),
+            ToneMapOperator::ExtendedReinhard(white) => {
+                let white_squared = white * white;
+                scale_luminance(&c, |l| l * (1.0 + l / white_squared) / (1.0 + l))
+            }
+            ToneMapOperator::Aces => map_channels(&c, |x| {
+                // The fit expects radiance scaled down to ACES' mid grey.
+                let x = 0.6 * x;
+                x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+            ToneMapOperator::Hable => {
+                const EXPOSURE_BIAS: f64 = 2.0;
+                const WHITE_POINT: f64 = 11.2;
+                let white_scale = 1.0 / hable_partial(WHITE_POINT);
+                map_channels(&c, |x| hable_partial(EXPOSURE_BIAS * x) * white_scale)
+            }
+        };
+        map_channels(&mapped, |x| clamp(x, 0.0, 1.0))
+    }
+}
+
+fn map_channels<F: Fn(f64) -> f64>(c: &Color, f: F) -> Color {
+    Color::new(f(c.x()), f(c.y()), f(c.z()))
+}
+
+fn scale_luminance<F: Fn(f64) -> f64>(c: &Color, f: F) -> Color {
+    let l = luminance(c);
+    if l <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    (f(l) / l) * c
+}
+
+fn hable_partial(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_map() {
+        let grey = Color::new(0.18, 0.18, 0.18);
+        let bright = Color::new(100.0, 50.0, 10.0);
+        let operators = [
+            ToneMapOperator::Clamp,
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::ExtendedReinhard(4.0),
+            ToneMapOperator::Aces,
+            ToneMapOperator::Hable,
+        ];
+        for operator in operators.iter() {
+            let mapper = ToneMapper::new(*operator, 0.0);
+            let (g, b) = (mapper.map(&grey), mapper.map(&bright));
+            assert!(g.x() > 0.0 && g.x() < b.x() && b.x() <= 1.0);
+        }
+
+        let reinhard = ToneMapper::new(ToneMapOperator::ExtendedReinhard(4.0), 1.0);
+        assert!((reinhard.map(&Color::new(2.0, 2.0, 2.0)).y() - 1.0).abs() < 1e-9);
+        assert!(ToneMapper::new(ToneMapOperator::Clamp, -1.0).map(&grey).x() == 0.09);
+    }
+}
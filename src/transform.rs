@@ -0,0 +1,334 @@
+use super::*;
+use std::ops;
+use std::sync::Arc;
+
+// A 4x4 affine transform acting on column vectors. Composition with `*`
+// applies the right-hand transform first, so `&translate * &rotate`
+// rotates an object and then moves it.
+#[derive(Clone)]
+pub struct Transform {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn identity() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translate(offset: &Vec3) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(factors: &Vec3) -> Self {
+        Self::new([
+            [factors.x(), 0.0, 0.0, 0.0],
+            [0.0, factors.y(), 0.0, 0.0],
+            [0.0, 0.0, factors.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(&Vec3(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(&Vec3(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(&Vec3(0.0, 0.0, 1.0), degrees)
+    }
+
+    // Right-handed rotation by the given angle around an arbitrary axis.
+    pub fn rotate(axis: &Vec3, degrees: f64) -> Self {
+        let a = unit_vector(axis);
+        let theta = degrees_to_radians(degrees);
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let t = 1.0 - cos_theta;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        Self::new([
+            [
+                t * x * x + cos_theta,
+                t * x * y - sin_theta * z,
+                t * x * z + sin_theta * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin_theta * z,
+                t * y * y + cos_theta,
+                t * y * z - sin_theta * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin_theta * y,
+                t * y * z + sin_theta * x,
+                t * z * z + cos_theta,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().enumerate() {
+                *e = self.m[j][i];
+            }
+        }
+        Self::new(m)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting. Returns None for a
+    // singular or non-finite matrix, e.g. a zero scale factor.
+    pub fn inverse(&self) -> Option<Self> {
+        if self.m.iter().flatten().any(|e| !e.is_finite()) {
+            return None;
+        }
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+        let inv = Self::new(inv);
+        if inv.m.iter().flatten().any(|e| !e.is_finite()) {
+            return None;
+        }
+        Some(inv)
+    }
+
+    // Determinant of the linear (upper-left 3x3) part.
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+// Transform * Transform
+impl ops::Mul<&Transform> for &Transform {
+    type Output = Transform;
+    fn mul(self, rhs: &Transform) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().enumerate() {
+                *e = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Transform::new(m)
+    }
+}
+
+// Places an object in the world. Rays are carried into the object's own
+// space, and hit points and normals are carried back out.
+pub struct Transformed {
+    pub object: Arc<dyn Hitable>,
+    pub object_to_world: Transform,
+    pub world_to_object: Transform,
+    pub normal_to_world: Transform,
+    pub bbox: Option<Aabb>,
+}
+
+impl Transformed {
+    // Returns None when the transform cannot be inverted.
+    pub fn new(object: Arc<dyn Hitable>, transform: Transform) -> Option<Self> {
+        let world_to_object = transform.inverse()?;
+        // Normals transform by the inverse transpose.
+        let normal_to_world = world_to_object.transpose();
+
+        let mut object_box: Aabb = Default::default();
+        let bbox = if object.bounding_box(0.0, 1.0, &mut object_box) {
+            Some(Self::transform_box(&transform, &object_box))
+        } else {
+            None
+        };
+
+        Some(Self {
+            object,
+            object_to_world: transform,
+            world_to_object,
+            normal_to_world,
+            bbox,
+        })
+    }
+
+    fn transform_box(transform: &Transform, b: &Aabb) -> Aabb {
+        let mut output: Aabb = Default::default();
+        for i in 0..8 {
+            let corner = Point3::new(
+                if i & 1 == 0 { b.min().x() } else { b.max().x() },
+                if i & 2 == 0 { b.min().y() } else { b.max().y() },
+                if i & 4 == 0 { b.min().z() } else { b.max().z() },
+            );
+            let p = transform.transform_point(&corner);
+            output = surrounding_box(&output, &Aabb::new(p.clone(), p));
+        }
+        output
+    }
+}
+
+impl Hitable for Transformed {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        // The direction is not renormalized, so t is the same in both spaces.
+        let object_r = Ray::new(
+            self.world_to_object.transform_point(r.origin()),
+            self.world_to_object.transform_vector(r.direction()),
+            r.time(),
+        );
+
+        if !self.object.hit(&object_r, t_min, t_max, rec) {
+            return false;
+        }
+
+        rec.p = self.object_to_world.transform_point(&rec.p);
+        rec.normal = unit_vector(&self.normal_to_world.transform_vector(&rec.normal));
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        match &self.bbox {
+            Some(bbox) => {
+                output_box.assign(bbox);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        // Carry the density over from object space. A linear map A changes
+        // solid angle around unit direction w by |det A| / |A w|^3.
+        let object_origin = self.world_to_object.transform_point(origin);
+        let w = unit_vector(v);
+        let object_v = self.world_to_object.transform_vector(&w);
+        let object_pdf = self.object.pdf_value(&object_origin, &object_v);
+        if object_pdf == 0.0 {
+            return 0.0;
+        }
+        let length = object_v.length();
+        object_pdf * self.world_to_object.determinant3().abs() / (length * length * length)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let object_origin = self.world_to_object.transform_point(origin);
+        self.object_to_world
+            .transform_vector(&self.object.random(&object_origin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Vec3, b: &Vec3) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn test_compose() {
+        let t = &Transform::translate(&Vec3(1.0, 2.0, 3.0)) * &Transform::rotate_y(90.0);
+        let p = t.transform_point(&Point3::new(1.0, 0.0, 0.0));
+        assert!(close(&p, &Point3::new(1.0, 2.0, 2.0)));
+        let v = t.transform_vector(&Vec3(1.0, 0.0, 0.0));
+        assert!(close(&v, &Vec3(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = &(&Transform::translate(&Vec3(1.0, -2.0, 3.0))
+            * &Transform::rotate(&Vec3(1.0, 1.0, 0.0), 33.0))
+            * &Transform::scale(&Vec3(2.0, 0.5, 3.0));
+        let inv = t.inverse().unwrap();
+        let p = Point3::new(0.3, -0.7, 1.1);
+        assert!(close(&inv.transform_point(&t.transform_point(&p)), &p));
+        assert!(Transform::scale(&Vec3(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Transform::rotate(&Vec3(0.0, 0.0, 0.0), 30.0)
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn test_transformed_hit() {
+        let sphere = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(UninitMaterial {}),
+        ));
+        let t =
+            &Transform::translate(&Vec3(0.0, 0.0, -5.0)) * &Transform::scale(&Vec3(2.0, 1.0, 1.0));
+        let object = Transformed::new(sphere, t).unwrap();
+
+        let mut rec: HitRecord = Default::default();
+        let r = Ray::new(Point3::new(-10.0, 0.0, -5.0), Vec3(1.0, 0.0, 0.0), 0.0);
+        assert!(object.hit(&r, 0.001, INFINITY, &mut rec));
+        assert!((rec.t - 8.0).abs() < 1e-9);
+        assert!(close(&rec.p, &Point3::new(-2.0, 0.0, -5.0)));
+        assert!(close(&rec.normal, &Vec3(-1.0, 0.0, 0.0)));
+
+        let mut bbox: Aabb = Default::default();
+        assert!(object.bounding_box(0.0, 1.0, &mut bbox));
+        assert!(close(bbox.min(), &Point3::new(-2.0, -1.0, -6.0)));
+    }
+}
@@ -0,0 +1,165 @@
+use super::*;
+use std::sync::Arc;
+
+// Möller–Trumbore ray/triangle intersection. Returns the ray parameter and
+// the barycentric coordinates of the hit point relative to p1 and p2.
+pub fn moller_trumbore(
+    r: &Ray,
+    p0: &Point3,
+    p1: &Point3,
+    p2: &Point3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let pvec = cross(r.direction(), &edge2);
+    let det = dot(&edge1, &pvec);
+
+    // The ray is parallel to the triangle's plane.
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = r.origin() - p0;
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = cross(&tvec, &edge1);
+    let b2 = dot(r.direction(), &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &qvec) * inv_det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+pub fn triangle_bounding_box(p0: &Point3, p1: &Point3, p2: &Point3) -> Aabb {
+    // Pad the box so that axis-aligned triangles never have zero thickness.
+    let delta = Vec3(0.0001, 0.0001, 0.0001);
+    let bbox = surrounding_box(
+        &Aabb::new(p0.clone(), p0.clone()),
+        &Aabb::new(p1.clone(), p1.clone()),
+    );
+    let bbox = surrounding_box(&bbox, &Aabb::new(p2.clone(), p2.clone()));
+    Aabb::new(bbox.min() - &delta, bbox.max() + &delta)
+}
+
+pub fn barycentric_interp(a: &Vec3, b: &Vec3, c: &Vec3, b1: f64, b2: f64) -> Vec3 {
+    &(&((1.0 - b1 - b2) * a) + &(b1 * b)) + &(b2 * c)
+}
+
+pub struct Triangle {
+    pub vertices: [Point3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub mat_ptr: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(p0: Point3, p1: Point3, p2: Point3, mat_ptr: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [p0, p1, p2],
+            normals: None,
+            mat_ptr,
+        }
+    }
+
+    // Vertex normals are interpolated across the face for smooth shading.
+    pub fn with_normals(
+        vertices: [Point3; 3],
+        normals: [Vec3; 3],
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            vertices,
+            normals: Some(normals),
+            mat_ptr,
+        }
+    }
+}
+
+impl Hitable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let [p0, p1, p2] = &self.vertices;
+        let (t, b1, b2) = match moller_trumbore(r, p0, p1, p2, t_min, t_max) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let outward_normal = match &self.normals {
+            Some([n0, n1, n2]) => unit_vector(&barycentric_interp(n0, n1, n2, b1, b2)),
+            None => unit_vector(&cross(&(p1 - p0), &(p2 - p0))),
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = b1;
+        rec.v = b2;
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat_ptr = self.mat_ptr.clone();
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let [p0, p1, p2] = &self.vertices;
+        output_box.assign(&triangle_bounding_box(p0, p1, p2));
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let [p0, p1, p2] = &self.vertices;
+        let (t, _, _) = match moller_trumbore(
+            &Ray::new(origin.clone(), v.clone(), 0.0),
+            p0,
+            p1,
+            p2,
+            0.001,
+            INFINITY,
+        ) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+
+        let n = cross(&(p1 - p0), &(p2 - p0));
+        let area = 0.5 * n.length();
+        let distance_squared = t * t * v.length_squared();
+        let cosine = (dot(v, &n) / (v.length() * n.length())).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        // Uniform over the triangle's area.
+        let [p0, p1, p2] = &self.vertices;
+        let (r1, r2) = sample_2d();
+        let su = r1.sqrt();
+        let p = barycentric_interp(p0, p1, p2, su * (1.0 - r2), su * r2);
+        &p - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moller_trumbore() {
+        let p0 = Point3::new(0.0, 0.0, 0.0);
+        let p1 = Point3::new(1.0, 0.0, 0.0);
+        let p2 = Point3::new(0.0, 1.0, 0.0);
+        let r = Ray::new(Point3::new(0.25, 0.5, 2.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        let (t, b1, b2) = moller_trumbore(&r, &p0, &p1, &p2, 0.001, INFINITY).unwrap();
+        assert!(t == 2.0 && b1 == 0.25 && b2 == 0.5);
+
+        let r = Ray::new(Point3::new(0.75, 0.5, 2.0), Vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(moller_trumbore(&r, &p0, &p1, &p2, 0.001, INFINITY).is_none());
+        let r = Ray::new(Point3::new(0.25, 0.5, 2.0), Vec3(1.0, 0.0, 0.0), 0.0);
+        assert!(moller_trumbore(&r, &p0, &p1, &p2, 0.001, INFINITY).is_none());
+    }
+}
@@ -0,0 +1,116 @@
+use super::*;
+use std::sync::Arc;
+
+// One triangle of a mesh, as indices into the mesh's shared vertex arrays.
+#[derive(Clone)]
+pub struct MeshFace {
+    pub vertices: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub texcoords: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub texcoords: Vec<(f64, f64)>,
+    pub faces: Vec<MeshFace>,
+    pub materials: Vec<Arc<dyn Material>>,
+}
+
+// A triangle that refers back into the shared mesh data instead of owning
+// copies of its vertices.
+pub struct MeshTriangle {
+    pub mesh: Arc<MeshData>,
+    pub face: usize,
+}
+
+impl Hitable for MeshTriangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let face = &self.mesh.faces[self.face];
+        let [i0, i1, i2] = face.vertices;
+        let p0 = &self.mesh.positions[i0];
+        let p1 = &self.mesh.positions[i1];
+        let p2 = &self.mesh.positions[i2];
+        let (t, b1, b2) = match moller_trumbore(r, p0, p1, p2, t_min, t_max) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let outward_normal = match face.normals {
+            Some([n0, n1, n2]) => unit_vector(&barycentric_interp(
+                &self.mesh.normals[n0],
+                &self.mesh.normals[n1],
+                &self.mesh.normals[n2],
+                b1,
+                b2,
+            )),
+            None => unit_vector(&cross(&(p1 - p0), &(p2 - p0))),
+        };
+        let (u, v) = match face.texcoords {
+            Some([t0, t1, t2]) => {
+                let (uv0, uv1, uv2) = (
+                    self.mesh.texcoords[t0],
+                    self.mesh.texcoords[t1],
+                    self.mesh.texcoords[t2],
+                );
+                let b0 = 1.0 - b1 - b2;
+                (
+                    b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                    b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+                )
+            }
+            None => (b1, b2),
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = u;
+        rec.v = v;
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat_ptr = self.mesh.materials[face.material].clone();
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let [i0, i1, i2] = self.mesh.faces[self.face].vertices;
+        output_box.assign(&triangle_bounding_box(
+            &self.mesh.positions[i0],
+            &self.mesh.positions[i1],
+            &self.mesh.positions[i2],
+        ));
+        true
+    }
+}
+
+pub struct TriangleMesh {
+    pub bvh: HitableList,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: MeshData) -> Self {
+        if mesh.faces.is_empty() {
+            panic!("No faces in TriangleMesh constructor.");
+        }
+        let mesh = Arc::new(mesh);
+        let mut triangles = HitableList::new();
+        for face in 0..mesh.faces.len() {
+            triangles.add(Arc::new(MeshTriangle {
+                mesh: mesh.clone(),
+                face,
+            }));
+        }
+        let bvh = build_bvh(&triangles, 0.0, 1.0);
+        Self { bvh }
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, t_min, t_max, rec)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.bvh.bounding_box(time0, time1, output_box)
+    }
+}
@@ -236,45 +236,58 @@ pub fn unit_vector(v: &Vec3) -> Vec3 {
 }
 
 pub fn random_unit_vector() -> Vec3 {
-    let a = random_double_minmax(0.0, 2.0 * PI);
-    let z = random_double_minmax(-1.0, 1.0);
+    let (r1, r2) = sample_2d();
+    let a = 2.0 * PI * r1;
+    let z = 2.0 * r2 - 1.0;
     let r = (1.0 - z * z).sqrt();
     Vec3(r * a.cos(), r * a.sin(), z)
 }
 
-pub fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new(
-            random_double_minmax(-1.0, 1.0),
-            random_double_minmax(-1.0, 1.0),
-            0.0,
-        );
-        if p.length_squared() >= 1.0 {
-            continue;
-        }
-        return p;
-    }
+// Cosine-weighted direction about +z.
+pub fn random_cosine_direction() -> Vec3 {
+    let (r1, r2) = sample_2d();
+    let z = (1.0 - r2).sqrt();
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+
+    Vec3(x, y, z)
 }
 
-pub fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = Vec3::random_minmax(-1.0, 1.0);
-        if p.length_squared() >= 1.0 {
-            continue;
-        }
-        return p;
-    }
+// Uniform direction within the cone subtended by a sphere, about +z.
+pub fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
+    let (r1, r2) = sample_2d();
+    let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+
+    Vec3(x, y, z)
 }
 
-pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
-    let in_unit_sphere = random_in_unit_sphere();
-    if dot(&in_unit_sphere, normal) > 0.0
-    // In the same hemisphere as the normal
-    {
-        in_unit_sphere
-    } else {
-        -&in_unit_sphere
+// Shirley and Chiu's concentric mapping from the square, which keeps the
+// sampler's strata where rejection sampling would throw them away.
+pub fn random_in_unit_disk() -> Vec3 {
+    let (r1, r2) = sample_2d();
+    let (a, b) = (2.0 * r1 - 1.0, 2.0 * r2 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
     }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// Uniform in the unit ball, from a direction and a cube-root radius rather
+// than by rejection, so that it draws a fixed number of dimensions.
+pub fn random_in_unit_sphere() -> Vec3 {
+    let direction = random_unit_vector();
+    sample_1d().cbrt() * &direction
 }
 
 pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {